@@ -0,0 +1,189 @@
+//! Calls the methods of the contract wasm in the NEAR runtime, without a node,
+//! and reports the gas each call burnt.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use near_parameters::{RuntimeConfigStore, RuntimeFeesConfig};
+use near_parameters::vm::Config;
+use near_primitives_core::account::AccountContract;
+use near_primitives_core::types::{Balance, Gas};
+use near_primitives_core::version::PROTOCOL_VERSION;
+use near_vm_runner::ContractCode;
+use near_vm_runner::logic::mocks::mock_external::MockedExternal;
+use near_vm_runner::logic::types::ReturnData;
+use near_vm_runner::logic::VMContext;
+use serde_json::{json, Value};
+
+pub const CONTRACT: &str = "checkers.near";
+
+// 0.01 NEAR, the smallest stake of a game
+pub const MIN_DEPOSIT: u128 = 10_000_000_000_000_000_000_000;
+
+// The most gas a transaction may attach
+pub const MAX_GAS: u64 = 300_000_000_000_000;
+
+pub const TERA_GAS: u64 = 1_000_000_000_000;
+
+/// The contract built for wasm, `CHECKERS_WASM` or the release build of `cargo build`
+pub fn contract_wasm() -> Vec<u8> {
+    let path = std::env::var("CHECKERS_WASM").unwrap_or_else(|_|
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../target/wasm32-unknown-unknown/release/checkers.wasm").to_string());
+    std::fs::read(&path).unwrap_or_else(|error|
+        panic!("Can't read {}, build the contract with `cargo build --target wasm32-unknown-unknown --release`: {}",
+               path, error))
+}
+
+/// The result of a call: the gas it burnt, the part of it spent running the
+/// wasm instructions, the JSON it returned and why it failed, if it did
+pub struct Outcome {
+    pub gas: u64,
+    pub wasm_gas: u64,
+    pub value: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// The contract deployed on its own account, its storage kept between calls
+pub struct Sim {
+    pub ext: MockedExternal,
+    config: Arc<Config>,
+    fees: Arc<RuntimeFeesConfig>,
+    timestamp: u64,
+    seed: u64,
+}
+
+impl Sim {
+    /// Deploys the contract and calls `new`
+    pub fn deploy() -> Sim {
+        let runtime_config = RuntimeConfigStore::new(None).get_config(PROTOCOL_VERSION).clone();
+        let mut sim = Sim {
+            ext: MockedExternal::with_code(ContractCode::new(contract_wasm(), None)),
+            config: runtime_config.wasm_config.clone(),
+            fees: runtime_config.fees.clone(),
+            timestamp: 1_700_000_000_000_000_000,
+            seed: 1,
+        };
+        sim.must(CONTRACT, "new", json!({}), 0);
+        sim
+    }
+
+    /// Calls `method` with the most gas a transaction may attach, each call a second after the last one
+    pub fn call(&mut self, predecessor: &str, method: &str, args: Value, deposit: u128) -> Outcome {
+        self.timestamp += 1_000_000_000;
+        let random_seed = self.next_random().to_le_bytes().repeat(4);
+        let context = VMContext {
+            current_account_id: CONTRACT.parse().unwrap(),
+            signer_account_id: predecessor.parse().unwrap(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id: predecessor.parse().unwrap(),
+            refund_to_account_id: predecessor.parse().unwrap(),
+            input: Rc::from(serde_json::to_vec(&args).unwrap()),
+            promise_results: Vec::new().into(),
+            block_height: 10,
+            block_timestamp: self.timestamp,
+            epoch_height: 1,
+            account_balance: Balance::from_near(1_000_000),
+            account_locked_balance: Balance::ZERO,
+            storage_usage: 100_000,
+            account_contract: AccountContract::None,
+            attached_deposit: Balance::from_yoctonear(deposit),
+            prepaid_gas: Gas::from_gas(MAX_GAS),
+            random_seed,
+            view_config: None,
+            output_data_receivers: vec![],
+        };
+
+        let gas_counter = context.make_gas_counter(&self.config);
+        let prepared = near_vm_runner::prepare(&self.ext, self.config.clone(), None, gas_counter, method);
+        let outcome = near_vm_runner::run(prepared, &mut self.ext, &context, self.fees.clone())
+            .expect("The runtime runs the contract");
+
+        let mut profile = outcome.profile.clone();
+        profile.compute_wasm_instruction_cost(outcome.burnt_gas);
+        let value = match &outcome.return_data {
+            ReturnData::Value(bytes) if !bytes.is_empty() => serde_json::from_slice(bytes).ok(),
+            _ => None,
+        };
+        Outcome {
+            gas: outcome.burnt_gas.as_gas(),
+            wasm_gas: profile.get_wasm_cost().as_gas(),
+            value,
+            error: outcome.aborted.map(|error| format!("{:?}", error)),
+        }
+    }
+
+    /// Calls `method` and panics if it fails
+    pub fn must(&mut self, predecessor: &str, method: &str, args: Value, deposit: u128) -> Outcome {
+        let outcome = self.call(predecessor, method, args, deposit);
+        if let Some(error) = &outcome.error {
+            panic!("{} failed: {}", method, error);
+        }
+        outcome
+    }
+
+    /// A view call, returning what the method returned
+    pub fn view(&mut self, method: &str, args: Value) -> Value {
+        self.must(CONTRACT, method, args, 0).value.unwrap_or(Value::Null)
+    }
+
+    /// Replaces `old` with `new` in the values stored by the contract, to set
+    /// up positions the tests can't reach by playing. `old` must be found once.
+    pub fn replace_in_state(&mut self, old: &[u8], new: &[u8]) {
+        let mut found = 0;
+        for value in self.ext.fake_trie.values_mut() {
+            if let Some(index) = value.windows(old.len()).position(|window| window == old) {
+                value.splice(index..index + old.len(), new.iter().copied());
+                found += 1;
+            }
+        }
+        assert_eq!(1, found, "The bytes to replace are stored {} times", found);
+    }
+
+    /// The same numbers on every run, to pick the moves of the tested games
+    pub fn next_random(&mut self) -> u64 {
+        self.seed = self.seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.seed >> 33
+    }
+
+    /// The legal moves of the player to move in the notation of `make_move`:
+    /// every complete capture when there are some, otherwise the simple moves
+    pub fn legal_lines(&mut self, game_id: u64) -> Vec<String> {
+        let moves = self.view("get_available_moves", json!({ "game_id": game_id }));
+        let mut lines = Vec::new();
+        for jump in moves[1].as_array().unwrap() {
+            jump_lines(jump, &mut Vec::new(), &mut lines);
+        }
+        if lines.is_empty() {
+            for simple in moves[0].as_array().unwrap() {
+                lines.push(format!("{} {}", square(&simple["from_row"], &simple["from_col"]),
+                                   square(&simple["to_row"], &simple["to_col"])));
+            }
+        }
+        lines
+    }
+}
+
+// The name of a square in the algebraic notation `make_move` reads
+fn square(row: &Value, column: &Value) -> String {
+    format!("{}{}", (b'a' + column.as_u64().unwrap() as u8) as char, row.as_u64().unwrap() + 1)
+}
+
+// Every path from the root of a jump tree to one of its leaves
+fn jump_lines(jump: &Value, prefix: &mut Vec<String>, lines: &mut Vec<String>) {
+    prefix.push(square(&jump["from_row"], &jump["from_col"]));
+    let jumps = jump["jumps"].as_array().unwrap();
+    if jumps.is_empty() {
+        lines.push(prefix.join(" "));
+    }
+    for next in jumps {
+        jump_lines(next, prefix, lines);
+    }
+    prefix.pop();
+}
+
+/// The mean and the largest gas of the calls, in Tgas
+pub fn mean_and_max(gas: &[u64]) -> (f64, f64) {
+    let total: u64 = gas.iter().sum();
+    (total as f64 / gas.len() as f64 / TERA_GAS as f64,
+     *gas.iter().max().unwrap() as f64 / TERA_GAS as f64)
+}
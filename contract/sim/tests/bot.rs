@@ -0,0 +1,62 @@
+use checkers::{board_to_fen, GameVariant, PackedBoard, parse_fen, Player};
+use checkers_sim::*;
+use near_sdk::borsh::BorshSerialize;
+use serde_json::json;
+
+const PLAYER: &str = "alice.near";
+
+// The gas a move against the bot must attach, less the gas the search leaves
+// to save the game: a bot move burning more would have its search cut short
+const BOT_SEARCH_GAS: u64 = 110 * TERA_GAS;
+
+// Funds the house pool and starts an International game where the player moves first
+fn start_bot_game() -> Sim {
+    let mut sim = Sim::deploy();
+    sim.must(CONTRACT, "fund_house_pool", json!({}), 100 * MIN_DEPOSIT);
+    sim.must(PLAYER, "make_available",
+             json!({"config": {"first_move": "First", "variant": "International", "vs_bot": true}, "referrer_id": null}),
+             MIN_DEPOSIT);
+    sim
+}
+
+// The board of a position packed as the contract stores it
+fn packed_board(fen: &str) -> Vec<u8> {
+    let variant = GameVariant::International;
+    let position = parse_fen(fen, variant).unwrap();
+    PackedBoard::pack(&position.board.into(), variant.rules()).try_to_vec().unwrap()
+}
+
+// Plays the first legal move of the player, which the bot answers in the same call
+fn gas_of_move_against_bot(sim: &mut Sim) -> u64 {
+    let lines = sim.legal_lines(0);
+    let outcome = sim.must(PLAYER, "make_move", json!({"game_id": 0, "line": lines[0]}), 0);
+    assert_eq!(json!(PLAYER), sim.view("get_active_player", json!({"game_id": 0})), "The bot answered");
+    outcome.gas
+}
+
+#[test]
+fn gas_of_bot_move_in_opening() {
+    let mut sim = start_bot_game();
+    let gas = gas_of_move_against_bot(&mut sim);
+
+    println!("International opening: {:.1} Tgas", gas as f64 / TERA_GAS as f64);
+    assert!(gas < BOT_SEARCH_GAS, "The move burnt {:.1} Tgas", gas as f64 / TERA_GAS as f64);
+}
+
+#[test]
+fn gas_of_bot_move_with_many_kings() {
+    let variant = GameVariant::International;
+    let start_board = variant.rules().new_board(&Player { id: 1 }, &Player { id: 2 }).into();
+    let start = packed_board(&board_to_fen(&start_board, variant, 0));
+
+    for fen in ["W:WK36-50:BK1-15",
+                "W:WK41-48:BK1-8",
+                "W:WK23,K28,K34,K36,K39,K43,K47,K50,31,32:BK1,K3,K5,K8,K12,K14,K17,K22,19,20"] {
+        let mut sim = start_bot_game();
+        sim.replace_in_state(&start, &packed_board(fen));
+        let gas = gas_of_move_against_bot(&mut sim);
+
+        println!("{}: {:.1} Tgas", fen, gas as f64 / TERA_GAS as f64);
+        assert!(gas < BOT_SEARCH_GAS, "The move burnt {:.1} Tgas from {}", gas as f64 / TERA_GAS as f64, fen);
+    }
+}
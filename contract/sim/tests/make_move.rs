@@ -0,0 +1,40 @@
+use checkers_sim::*;
+use serde_json::json;
+
+// Plays the same American game on every run, each move picked among the legal
+// ones, and reports the gas of `make_move` and of `get_available_moves`:
+// cargo test --release -- --nocapture
+#[test]
+fn gas_of_make_move() {
+    let mut sim = Sim::deploy();
+    let config = json!({"first_move": "First"});
+    sim.must("alice.near", "make_available", json!({"config": config, "referrer_id": null}), MIN_DEPOSIT);
+    sim.must("bob.near", "make_available", json!({"config": config, "referrer_id": null}), MIN_DEPOSIT);
+    sim.must("bob.near", "start_game", json!({"opponent_id": "alice.near", "referrer_id": null}), 0);
+
+    let mut make_move_gas = Vec::new();
+    let mut make_move_wasm_gas = Vec::new();
+    let mut available_moves_gas = Vec::new();
+    for _ in 0..60 {
+        available_moves_gas.push(sim.must(CONTRACT, "get_available_moves", json!({"game_id": 0}), 0).gas);
+        let lines = sim.legal_lines(0);
+        if lines.is_empty() {
+            break;
+        }
+        let line = lines[sim.next_random() as usize % lines.len()].clone();
+        let player = sim.view("get_active_player", json!({"game_id": 0}));
+        let outcome = sim.must(player.as_str().unwrap(), "make_move", json!({"game_id": 0, "line": line}), 0);
+        make_move_gas.push(outcome.gas);
+        make_move_wasm_gas.push(outcome.wasm_gas);
+    }
+
+    let (make_move_mean, make_move_max) = mean_and_max(&make_move_gas);
+    let (make_move_wasm_mean, _) = mean_and_max(&make_move_wasm_gas);
+    let (available_moves_mean, available_moves_max) = mean_and_max(&available_moves_gas);
+    println!("make_move: {} moves, mean {:.3} Tgas, max {:.3} Tgas", make_move_gas.len(), make_move_mean, make_move_max);
+    println!("make_move running wasm: mean {:.3} Tgas", make_move_wasm_mean);
+    println!("get_available_moves: mean {:.3} Tgas, max {:.3} Tgas", available_moves_mean, available_moves_max);
+
+    assert!(make_move_gas.len() > 40, "The game ended after {} moves", make_move_gas.len());
+    assert!(make_move_max < 2.0, "make_move burnt {:.3} Tgas", make_move_max);
+}
@@ -5,6 +5,12 @@ use crate::*;
 use crate::board::*;
 use crate::player::Player;
 
+pub mod bitboard;
+pub mod eval;
+pub mod perft;
+pub mod search;
+pub mod tablebase;
+
 #[derive(BorshDeserialize, BorshSerialize, Copy, Clone)]
 pub enum Direction {
     /// The piece is moving such that its rank is increasing
@@ -15,7 +21,7 @@ pub enum Direction {
 }
 
 // A move from one tile to an adjacent diagonal one
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SimpleMove {
     from_row: usize,
@@ -56,7 +62,7 @@ impl SimpleMove {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct JumpMove {
     from_row: usize,
@@ -94,6 +100,90 @@ impl JumpMove {
             false
         }
     }
+
+    /// Returns the number of pieces captured by the longest jump sequence of this tree.
+    pub fn max_captures(&self) -> usize {
+        self.jumps.iter()
+            .map(|subtree| subtree.max_captures() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Removes the jump sequences that capture fewer than `captures` pieces.
+    pub fn retain_captures(&mut self, captures: usize) {
+        if captures == 0 {
+            return;
+        }
+
+        self.jumps.retain(|subtree| subtree.max_captures() + 1 >= captures);
+        for subtree in self.jumps.iter_mut() {
+            subtree.retain_captures(captures - 1);
+        }
+    }
+
+    /// Returns every jump sequence of this tree, from the root down to a leaf.
+    pub fn sequences(&self) -> Vec<Vec<BoardPosition>> {
+        let position = BoardPosition::new(self.from_row, self.from_col);
+        if self.jumps.is_empty() {
+            return vec![vec![position]];
+        }
+
+        self.jumps.iter()
+            .flat_map(|subtree| subtree.sequences())
+            .map(|mut sequence| {
+                sequence.insert(0, position);
+                sequence
+            })
+            .collect()
+    }
+
+    /// Builds the tree of a single jump sequence, starting from the square
+    /// of the jumping piece.
+    pub fn from_sequence(sequence: &[BoardPosition]) -> JumpMove {
+        let mut jump_move = JumpMove::new(sequence[0].row, sequence[0].column);
+        if sequence.len() > 1 {
+            jump_move.jumps.push(JumpMove::from_sequence(&sequence[1..]));
+        }
+        jump_move
+    }
+
+    /// Removes the jump sequences, from the root down to a leaf, for which
+    /// `keep` returns false. Returns false if no sequence is left.
+    pub fn retain_sequences<F>(&mut self, keep: &F) -> bool
+        where F: Fn(&[BoardPosition]) -> bool {
+        let mut sequence = Vec::new();
+        self.retain_sequences_recursive(&mut sequence, keep)
+    }
+
+    fn retain_sequences_recursive<F>(&mut self, sequence: &mut Vec<BoardPosition>, keep: &F) -> bool
+        where F: Fn(&[BoardPosition]) -> bool {
+        sequence.push(BoardPosition::new(self.from_row, self.from_col));
+
+        let retained = if self.jumps.is_empty() {
+            keep(sequence)
+        } else {
+            self.jumps.retain_mut(|subtree| subtree.retain_sequences_recursive(sequence, keep));
+            !self.jumps.is_empty()
+        };
+
+        sequence.pop();
+        retained
+    }
+
+    /// Checks that the sequence follows the tree from its root down to
+    /// a leaf, that is, the jumping piece can't capture any further.
+    pub fn contains_complete_jump_sequence(&self, jumps: &[BoardPosition]) -> bool {
+        match jumps.first() {
+            Some(position) if position.row == self.from_row && position.column == self.from_col =>
+                if jumps.len() == 1 {
+                    self.jumps.is_empty()
+                } else {
+                    self.jumps.iter()
+                        .any(|subtree| subtree.contains_complete_jump_sequence(&jumps[1..]))
+                },
+            _ => false
+        }
+    }
 }
 
 /// Given the position of a main piece on a board, and the
@@ -415,6 +505,342 @@ fn push_simple_move_if_valid
     }
 }
 
+// The four diagonal directions as (row, column) steps
+const DIAGONAL_STEPS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+// The four orthogonal directions as (row, column) steps
+const ORTHOGONAL_STEPS: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+// The forward and sideways steps of a man moving orthogonally
+fn orthogonal_steps_for_man(direction: Direction) -> [(isize, isize); 3] {
+    match direction {
+        Direction::IncreasingRank => [(1, 0), (0, -1), (0, 1)],
+        Direction::DecreasingRank => [(-1, 0), (0, -1), (0, 1)],
+    }
+}
+
+/// Given the position of a flying king on a board, determines the simple
+/// moves available to this piece. A flying king moves any number of
+/// empty squares along a diagonal.
+pub fn find_simple_moves_for_flying_king
+(board: &Board,
+ row: usize,
+ col: usize)
+ -> Vec<SimpleMove> {
+    find_simple_moves_along_steps(board, row, col, &DIAGONAL_STEPS, true)
+}
+
+/// Given the position of a man piece moving orthogonally, and the
+/// direction this man piece is moving, determines the simple moves
+/// available to this piece. The man moves one square forward or sideways.
+pub fn find_orthogonal_simple_moves_for_man
+(board: &Board,
+ direction: Direction,
+ row: usize,
+ col: usize)
+ -> Vec<SimpleMove> {
+    find_simple_moves_along_steps(board, row, col, &orthogonal_steps_for_man(direction), false)
+}
+
+/// Given the position of a flying king moving orthogonally, determines
+/// the simple moves available to this piece. The king moves any number
+/// of empty squares along a row or a column.
+pub fn find_orthogonal_simple_moves_for_flying_king
+(board: &Board,
+ row: usize,
+ col: usize)
+ -> Vec<SimpleMove> {
+    find_simple_moves_along_steps(board, row, col, &ORTHOGONAL_STEPS, true)
+}
+
+fn find_simple_moves_along_steps
+(board: &Board,
+ row: usize,
+ col: usize,
+ steps: &[(isize, isize)],
+ flying: bool)
+ -> Vec<SimpleMove> {
+    let start = BoardPosition::new(row, col);
+    let mut moves = Vec::new();
+
+    for step in steps {
+        let mut position = step_position(board, start, *step);
+        while let Some(end) = position {
+            if board.get_tile(end.row, end.column).get_piece().is_some() {
+                break;
+            }
+            moves.push(SimpleMove::new(row, col, end.row, end.column));
+            if !flying {
+                break;
+            }
+            position = step_position(board, end, *step);
+        }
+    }
+
+    moves
+}
+
+/// Given the position of a man piece that captures forward and
+/// backward, determines the jumps available to this piece.
+pub fn find_jump_moves_for_man_in_all_directions
+(board: &Board,
+ player: &Player,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let capture_style = CaptureStyle {
+        steps: &DIAGONAL_STEPS,
+        flying: false,
+        promotion_row: None,
+        captures_kings: true,
+        removes_captured: false,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+/// Given the position of a man piece that captures forward and backward,
+/// determines the jumps available to this piece. Once the man lands on the
+/// last row, it continues capturing as a flying king.
+pub fn find_jump_moves_for_man_promoting_during_capture
+(board: &Board,
+ player: &Player,
+ direction: Direction,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let promotion_row = match direction {
+        Direction::IncreasingRank => board.number_rows() - 1,
+        Direction::DecreasingRank => 0,
+    };
+
+    let capture_style = CaptureStyle {
+        steps: &DIAGONAL_STEPS,
+        flying: false,
+        promotion_row: Some(promotion_row),
+        captures_kings: true,
+        removes_captured: false,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+/// Given the position of a man piece and the direction this man piece
+/// is moving, determines the jumps available to this piece when men
+/// are not allowed to capture kings.
+pub fn find_jump_moves_for_man_not_capturing_kings
+(board: &Board,
+ player: &Player,
+ direction: Direction,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let forward_steps = match direction {
+        Direction::IncreasingRank => [(1, -1), (1, 1)],
+        Direction::DecreasingRank => [(-1, -1), (-1, 1)],
+    };
+
+    let capture_style = CaptureStyle {
+        steps: &forward_steps,
+        flying: false,
+        promotion_row: None,
+        captures_kings: false,
+        removes_captured: false,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+/// Given the position of a flying king on a board, determines the jumps
+/// available to this piece. A flying king captures a piece at any distance
+/// along a diagonal and may land on any empty square behind it.
+pub fn find_jump_moves_for_flying_king
+(board: &Board,
+ player: &Player,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let capture_style = CaptureStyle {
+        steps: &DIAGONAL_STEPS,
+        flying: true,
+        promotion_row: None,
+        captures_kings: true,
+        removes_captured: false,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+/// Given the position of a man piece moving orthogonally, and the
+/// direction this man piece is moving, determines the jumps available
+/// to this piece. The man captures forward and sideways, and each
+/// captured piece is removed from the board as soon as it is jumped.
+pub fn find_orthogonal_jump_moves_for_man
+(board: &Board,
+ player: &Player,
+ direction: Direction,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let capture_style = CaptureStyle {
+        steps: &orthogonal_steps_for_man(direction),
+        flying: false,
+        promotion_row: None,
+        captures_kings: true,
+        removes_captured: true,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+/// Given the position of a flying king moving orthogonally, determines
+/// the jumps available to this piece. Each captured piece is removed from
+/// the board as soon as it is jumped, and the king may not turn back
+/// the way it came between two captures.
+pub fn find_orthogonal_jump_moves_for_flying_king
+(board: &Board,
+ player: &Player,
+ row: usize,
+ col: usize)
+ -> JumpMove {
+    let capture_style = CaptureStyle {
+        steps: &ORTHOGONAL_STEPS,
+        flying: true,
+        promotion_row: None,
+        captures_kings: true,
+        removes_captured: true,
+    };
+
+    find_jump_moves_along_steps(board, player, row, col, capture_style)
+}
+
+// Describes how a piece moves while capturing
+#[derive(Copy, Clone)]
+struct CaptureStyle<'a> {
+    /// Directions the piece may capture in, as (row, column) steps
+    steps: &'a [(isize, isize)],
+    /// Whether the piece captures at any distance
+    flying: bool,
+    /// Row on which the piece becomes a flying king in the middle of a capture
+    promotion_row: Option<usize>,
+    /// Whether the piece may capture kings
+    captures_kings: bool,
+    /// Whether jumped pieces are removed at once, instead of at the end of
+    /// the move. The piece may then not turn back the way it came.
+    removes_captured: bool,
+}
+
+// Unless they are removed at once, jumped pieces stay on the board until the
+// move is complete: they can't be jumped twice and block the way of the jumping piece.
+fn find_jump_moves_along_steps
+(board: &Board,
+ player: &Player,
+ row: usize,
+ col: usize,
+ capture_style: CaptureStyle)
+ -> JumpMove {
+    let mut jump_root = JumpMove::new(row, col);
+    let mut jumped_tiles = Vec::new();
+
+    find_jump_moves_along_steps_rustcursive(
+        board, player, BoardPosition::new(row, col), capture_style, None, &mut jump_root, &mut jumped_tiles);
+
+    jump_root
+}
+
+fn find_jump_moves_along_steps_rustcursive
+(board: &Board,
+ player: &Player,
+ init_position: BoardPosition,
+ capture_style: CaptureStyle,
+ last_step: Option<(isize, isize)>,
+ curr_jump_root: &mut JumpMove,
+ jumped_tiles: &mut Vec<BoardPosition>) {
+    let flying = capture_style.flying;
+    // the jumping piece "floats" above the board, so its initial position is empty
+    let is_empty = |position: BoardPosition, jumped_tiles: &[BoardPosition]| position == init_position
+        || (capture_style.removes_captured && jumped_tiles.contains(&position))
+        || board.get_tile(position.row, position.column).get_piece().is_none();
+
+    let start = BoardPosition::new(curr_jump_root.from_row, curr_jump_root.from_col);
+
+    for step in capture_style.steps {
+        if capture_style.removes_captured && last_step == Some((-step.0, -step.1)) {
+            continue;
+        }
+
+        // find the first piece in this direction
+        let mut pwnd_position = step_position(board, start, *step);
+        while let Some(position) = pwnd_position {
+            if !flying || !is_empty(position, jumped_tiles) {
+                break;
+            }
+            pwnd_position = step_position(board, position, *step);
+        }
+
+        let pwnd_position = match pwnd_position {
+            Some(position) if !is_empty(position, jumped_tiles) => position,
+            _ => continue
+        };
+
+        let pwnd_piece_capturable = board.get_tile(pwnd_position.row, pwnd_position.column)
+            .get_piece()
+            .map(|piece| piece.get_player_id() != player.id
+                && (capture_style.captures_kings || piece.get_type() == PieceType::Man))
+            .unwrap_or(false);
+
+        if !pwnd_piece_capturable || jumped_tiles.contains(&pwnd_position) {
+            continue;
+        }
+
+        let mut end_position = step_position(board, pwnd_position, *step);
+        while let Some(end) = end_position {
+            if !is_empty(end, jumped_tiles) {
+                break;
+            }
+
+            let mut jump = JumpMove::new(end.row, end.column);
+
+            let next_capture_style = if capture_style.promotion_row == Some(end.row) {
+                CaptureStyle { flying: true, ..capture_style }
+            } else {
+                capture_style
+            };
+
+            jumped_tiles.push(pwnd_position);
+            find_jump_moves_along_steps_rustcursive(
+                board, player, init_position, next_capture_style, Some(*step), &mut jump, jumped_tiles);
+            jumped_tiles.pop();
+
+            curr_jump_root.jumps.push(jump);
+
+            if !flying {
+                break;
+            }
+            end_position = step_position(board, end, *step);
+        }
+    }
+}
+
+// Moves a position one step in the given direction. Returns None
+// if the resulting position is off the board.
+fn step_position
+(board: &Board,
+ position: BoardPosition,
+ step: (isize, isize))
+ -> Option<BoardPosition> {
+    let row = position.row as isize + step.0;
+    let col = position.column as isize + step.1;
+
+    if row < 0 || col < 0
+        || row >= board.number_rows() as isize
+        || col >= board.number_columns() as isize {
+        None
+    } else {
+        Some(BoardPosition::new(row as usize, col as usize))
+    }
+}
+
 // This enum describes an offset direction and magnitude.
 enum TileOffset {
     Positive(usize),
@@ -475,4 +901,49 @@ fn is_tile_offset_in_bounds
 
     is_offset_value_in_range(start_row, max_row_index, row_offset)
         && is_offset_value_in_range(start_col, max_col_index, col_offset)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 3,3 -> 5,5 -> 7,3 or 7,7
+    fn get_jump_tree() -> JumpMove {
+        JumpMove::with_jumps(3, 3, vec![
+            JumpMove::with_jumps(5, 5, vec![
+                JumpMove::new(7, 3),
+                JumpMove::new(7, 7)])])
+    }
+
+    #[test]
+    fn jump_sequence_prefix() {
+        let tree = get_jump_tree();
+        let the_move = vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)];
+
+        assert!(tree.contains_jump_sequence(&the_move));
+        assert!(!tree.contains_complete_jump_sequence(&the_move));
+    }
+
+    #[test]
+    fn complete_jump_sequence() {
+        let tree = get_jump_tree();
+        let the_move = vec![
+            BoardPosition::new(3, 3),
+            BoardPosition::new(5, 5),
+            BoardPosition::new(7, 7)];
+
+        assert!(tree.contains_jump_sequence(&the_move));
+        assert!(tree.contains_complete_jump_sequence(&the_move));
+    }
+
+    #[test]
+    fn jump_sequence_not_in_tree() {
+        let tree = get_jump_tree();
+        let the_move = vec![
+            BoardPosition::new(3, 3),
+            BoardPosition::new(5, 1)];
+
+        assert!(!tree.contains_jump_sequence(&the_move));
+        assert!(!tree.contains_complete_jump_sequence(&the_move));
+    }
+}
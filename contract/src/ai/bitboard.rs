@@ -0,0 +1,327 @@
+use crate::*;
+use crate::player::Player;
+
+// A (row, column) step on the board
+type Step = (isize, isize);
+
+// The directions a king moves in, in the order the per-square generator tries them
+const KING_STEPS: [Step; 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+// The squares of the first column
+const FIRST_COLUMN: u64 = 0x0101_0101_0101_0101;
+
+/// An 8x8 board stored as bitboards: bit `row * 8 + column` of a mask is set
+/// when the square holds such a piece. Moves are found with a few shifts and
+/// masks over the whole board, instead of visiting the squares one by one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BitBoard {
+    /// Squares holding the pieces of player 1 and player 2
+    pieces: [u64; 2],
+    /// Squares holding a king of either player
+    kings: u64,
+}
+
+impl BitBoard {
+    pub fn new(board: &Board) -> BitBoard {
+        assert!(board.number_rows() == CHECKERBOARD_SIZE && board.number_columns() == CHECKERBOARD_SIZE,
+                "Bitboards only hold {0}x{0} boards", CHECKERBOARD_SIZE);
+
+        // an 8x8 board keeps its squares in the low 64 bits of its masks
+        let (pieces, kings) = board.masks();
+        BitBoard::from_masks([pieces[0] as u64, pieces[1] as u64], kings as u64)
+    }
+
+    /// A board from the squares of the pieces of each player, `kings` telling
+    /// which of them are kings
+    pub(crate) fn from_masks(pieces: [u64; 2], kings: u64) -> BitBoard {
+        BitBoard { pieces, kings }
+    }
+
+    fn empty(&self) -> u64 {
+        !(self.pieces[0] | self.pieces[1])
+    }
+
+    /// Determines the simple moves available to the pieces of `player`, as
+    /// American checkers are played. The moves are the same, and in the same
+    /// order, as the ones found by `find_simple_moves_for_man` and
+    /// `find_simple_moves_for_king` square by square.
+    pub fn find_simple_moves(&self, player: &Player, direction: Direction) -> Vec<SimpleMove> {
+        let own = self.pieces[player_index(player.id)];
+        let empty = self.empty();
+        let man_steps = man_steps(direction);
+
+        // pieces with at least one empty square next to them in a direction they move in
+        let movers = (own & !self.kings & can_step(&man_steps, empty))
+            | (own & self.kings & can_step(&KING_STEPS, empty));
+
+        let mut moves = Vec::new();
+        for square in squares(movers) {
+            let steps: &[Step] = if self.kings & square != 0 { &KING_STEPS } else { &man_steps };
+            for step in steps {
+                let target = shift(square, *step);
+                if target & empty != 0 {
+                    let (from_row, from_col) = square_position(square);
+                    let (to_row, to_col) = square_position(target);
+                    moves.push(SimpleMove::new(from_row, from_col, to_row, to_col));
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Determines the jumps available to the pieces of `player`, as American
+    /// checkers are played. Only the pieces that can capture get a jump tree.
+    /// The trees are the same, and in the same order, as the ones found by
+    /// `find_jump_moves_for_man` and `find_jump_moves_for_king` square by square.
+    pub fn find_jump_moves(&self, player: &Player, direction: Direction) -> Vec<JumpMove> {
+        let index = player_index(player.id);
+        let own = self.pieces[index];
+        let enemy = self.pieces[1 - index];
+        let empty = self.empty();
+        let man_steps = man_steps(direction);
+
+        // pieces with an enemy piece next to them and an empty square behind it
+        let jumpers = (own & !self.kings & can_jump(&man_steps, enemy, empty))
+            | (own & self.kings & can_jump(&KING_STEPS, enemy, empty));
+
+        squares(jumpers)
+            .map(|square| if self.kings & square != 0 {
+                let mut jump_root = jump_move(square);
+                find_king_jumps(&mut jump_root, square, enemy, empty | square, 0);
+                jump_root
+            } else {
+                let mut jump_root = jump_move(square);
+                find_man_jumps(&mut jump_root, square, &man_steps, enemy, empty);
+                jump_root
+            })
+            .collect()
+    }
+}
+
+// Men move forward only, to the left first
+fn man_steps(direction: Direction) -> [Step; 2] {
+    match direction {
+        Direction::IncreasingRank => [(1, -1), (1, 1)],
+        Direction::DecreasingRank => [(-1, -1), (-1, 1)],
+    }
+}
+
+// Men jump forward only, so they never come back to a square or jump a piece twice
+fn find_man_jumps(jump_root: &mut JumpMove, square: u64, steps: &[Step], enemy: u64, empty: u64) {
+    for step in steps {
+        let jumped = shift(square, *step);
+        let target = shift(jumped, *step);
+        if jumped & enemy != 0 && target & empty != 0 {
+            let mut jump = jump_move(target);
+            find_man_jumps(&mut jump, target, steps, enemy, empty);
+            jump_root.jumps.push(jump);
+        }
+    }
+}
+
+// `free` also holds the initial square of the king, which it may jump back to,
+// and `jumped` the pieces captured so far, which can't be captured again
+fn find_king_jumps(jump_root: &mut JumpMove, square: u64, enemy: u64, free: u64, jumped: u64) {
+    for step in KING_STEPS.iter() {
+        let captured = shift(square, *step) & enemy & !jumped;
+        let target = shift(captured, *step);
+        if target & free != 0 {
+            let mut jump = jump_move(target);
+            find_king_jumps(&mut jump, target, enemy, free, jumped | captured);
+            jump_root.jumps.push(jump);
+        }
+    }
+}
+
+// Squares from which a step in one of `steps` lands on `targets`
+fn can_step(steps: &[Step], targets: u64) -> u64 {
+    steps.iter()
+        .map(|&(row_step, col_step)| shift(targets, (-row_step, -col_step)))
+        .fold(0, |sources, step_sources| sources | step_sources)
+}
+
+// Squares from which a jump in one of `steps` captures on `enemy` and lands on `empty`
+fn can_jump(steps: &[Step], enemy: u64, empty: u64) -> u64 {
+    steps.iter()
+        .map(|&(row_step, col_step)| {
+            let back = (-row_step, -col_step);
+            shift(shift(empty, back) & enemy, back)
+        })
+        .fold(0, |sources, step_sources| sources | step_sources)
+}
+
+// Moves every square of the mask one step, dropping the ones leaving the board
+fn shift(mask: u64, (row_step, col_step): Step) -> u64 {
+    let columns_kept = (0..CHECKERBOARD_SIZE as isize)
+        .filter(|column| (0..CHECKERBOARD_SIZE as isize).contains(&(column + col_step)))
+        .fold(0, |columns, column| columns | (FIRST_COLUMN << column));
+
+    let mask = mask & columns_kept;
+    let offset = row_step * CHECKERBOARD_SIZE as isize + col_step;
+    if offset >= 0 {
+        mask << offset
+    } else {
+        mask >> -offset
+    }
+}
+
+// Iterates over the squares of a mask, in row by row order
+fn squares(mut mask: u64) -> impl Iterator<Item=u64> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            None
+        } else {
+            let square = mask & mask.wrapping_neg();
+            mask &= mask - 1;
+            Some(square)
+        }
+    })
+}
+
+fn square_position(square: u64) -> (usize, usize) {
+    let index = square.trailing_zeros() as usize;
+    (index / CHECKERBOARD_SIZE, index % CHECKERBOARD_SIZE)
+}
+
+fn jump_move(square: u64) -> JumpMove {
+    let (row, column) = square_position(square);
+    JumpMove::new(row, column)
+}
+
+// Player IDs are 1 and 2
+fn player_index(player_id: u32) -> usize {
+    player_id as usize - 1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::piece::ManPiece;
+
+    use super::*;
+
+    // American rules that find the moves square by square
+    struct PerSquareRules;
+
+    impl RuleSet for PerSquareRules {
+        fn board_size(&self) -> usize {
+            AmericanRules.board_size()
+        }
+
+        fn new_board(&self, player1: &Player, player2: &Player) -> board::BoardToSave {
+            AmericanRules.new_board(player1, player2)
+        }
+
+        fn find_simple_moves(&self,
+                             board: &Board,
+                             piece_type: PieceType,
+                             direction: Direction,
+                             row: usize,
+                             col: usize)
+                             -> Vec<SimpleMove> {
+            AmericanRules.find_simple_moves(board, piece_type, direction, row, col)
+        }
+
+        fn find_jump_moves(&self,
+                           board: &Board,
+                           player: &Player,
+                           piece_type: PieceType,
+                           direction: Direction,
+                           row: usize,
+                           col: usize)
+                           -> JumpMove {
+            AmericanRules.find_jump_moves(board, player, piece_type, direction, row, col)
+        }
+    }
+
+    // xorshift64, good enough to spread pieces over the board
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // Fills the dark squares at random: about half of them stay empty,
+    // and about a third of the pieces are kings
+    fn random_board(state: &mut u64) -> Board {
+        let mut board = Board::new(CHECKERBOARD_SIZE, CHECKERBOARD_SIZE);
+        for row in 0..CHECKERBOARD_SIZE {
+            for column in (row % 2..CHECKERBOARD_SIZE).step_by(2) {
+                let random = next_random(state);
+                let player = match random % 4 {
+                    0 => Player { id: 1 },
+                    1 => Player { id: 2 },
+                    _ => continue,
+                };
+                let piece: Box<dyn Piece> = if (random >> 2) % 3 == 1 {
+                    Box::new(KingPiece::new(&player))
+                } else {
+                    Box::new(ManPiece::new(&player))
+                };
+                board.set_tile(row, column, Box::new(OccupiedTile::new(piece)));
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn same_moves_as_per_square_generator() {
+        let players = [(Player { id: 1 }, Direction::IncreasingRank),
+            (Player { id: 2 }, Direction::DecreasingRank)];
+
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..5000 {
+            let board = random_board(&mut state);
+            let bit_board = BitBoard::new(&board);
+            for (player, direction) in players.iter() {
+                assert_eq!(PerSquareRules.find_all_simple_moves(&board, player, *direction),
+                           bit_board.find_simple_moves(player, *direction));
+                assert_eq!(PerSquareRules.find_all_jump_moves(&board, player, *direction),
+                           bit_board.find_jump_moves(player, *direction));
+            }
+        }
+    }
+
+    #[test]
+    fn same_moves_on_new_board() {
+        let (player1, player2) = (Player { id: 1 }, Player { id: 2 });
+        let board: Board = AmericanRules.new_board(&player1, &player2).into();
+
+        let moves = BitBoard::new(&board).find_simple_moves(&player1, Direction::IncreasingRank);
+        assert_eq!(PerSquareRules.find_all_simple_moves(&board, &player1, Direction::IncreasingRank), moves);
+        assert_eq!(7, moves.len());
+        assert!(BitBoard::new(&board).find_jump_moves(&player1, Direction::IncreasingRank).is_empty());
+    }
+
+    // Compares the time both generators take on random positions:
+    // cargo test --release generator_timing -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn generator_timing() {
+        let player = Player { id: 1 };
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        let boards: Vec<Board> = (0..10000).map(|_| random_board(&mut state)).collect();
+
+        let start = std::time::Instant::now();
+        let mut per_square_moves = 0;
+        for board in boards.iter() {
+            per_square_moves += PerSquareRules.find_all_simple_moves(board, &player, Direction::IncreasingRank).len();
+            per_square_moves += PerSquareRules.find_all_jump_moves(board, &player, Direction::IncreasingRank).len();
+        }
+        let per_square_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut bit_board_moves = 0;
+        for board in boards.iter() {
+            let bit_board = BitBoard::new(board);
+            bit_board_moves += bit_board.find_simple_moves(&player, Direction::IncreasingRank).len();
+            bit_board_moves += bit_board.find_jump_moves(&player, Direction::IncreasingRank).len();
+        }
+        let bit_board_time = start.elapsed();
+
+        assert_eq!(per_square_moves, bit_board_moves);
+        println!("per square: {:?}, bitboard: {:?} for {} positions",
+                 per_square_time, bit_board_time, boards.len());
+    }
+}
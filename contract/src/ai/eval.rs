@@ -0,0 +1,135 @@
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Weights of the static evaluation, in hundredths of a man
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EvalWeights {
+    /// Each man on the board
+    pub man: i32,
+    /// Each king on the board
+    pub king: i32,
+    /// Each man still on its own back rank, guarding it from promotions
+    pub back_rank: i32,
+    /// Each move the player may make
+    pub mobility: i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights { man: 100, king: 150, back_rank: 10, mobility: 2 }
+    }
+}
+
+/// The terms of the static evaluation of a position, each one the value for
+/// the first player less the value for the second player. In giveaway games,
+/// where losing pieces is the goal, the terms are reversed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Evaluation {
+    pub material: i32,
+    pub kings: i32,
+    pub back_rank: i32,
+    pub mobility: i32,
+    pub total: i32,
+}
+
+/// Evaluates a position without searching it
+pub fn evaluate_position(game: &Game, weights: &EvalWeights) -> Evaluation {
+    let board = &game.board;
+    let mut men = [0, 0];
+    let mut kings = [0, 0];
+    let mut back_rank_men = [0, 0];
+    let back_ranks = [0, board.number_rows() - 1];
+
+    for row in 0..board.number_rows() {
+        for column in 0..board.number_columns() {
+            if let Some(piece) = board.get_tile(row, column).get_piece() {
+                let index = piece.get_player_id() as usize - 1;
+                match piece.get_type() {
+                    PieceType::Man => {
+                        men[index] += 1;
+                        if row == back_ranks[index] {
+                            back_rank_men[index] += 1;
+                        }
+                    }
+                    PieceType::King => kings[index] += 1,
+                }
+            }
+        }
+    }
+
+    // the moves of the player to move are known, the opponent's are only found when they count
+    let mut moves = [0, 0];
+    if weights.mobility != 0 {
+        let current = game.current_player_index;
+        moves[current] = count_moves(game, &game.available_simple_moves, &game.available_jump_moves);
+
+        let opponent = &game.players[1 - current];
+        let rules = game.rules();
+        let simple_moves = rules.find_all_simple_moves(board, &opponent.player, opponent.direction);
+        let jump_moves = rules.filter_jump_moves(
+            board, rules.find_all_jump_moves(board, &opponent.player, opponent.direction));
+        moves[1 - current] = count_moves(game, &simple_moves, &jump_moves);
+    }
+
+    let sign = if game.variant == GameVariant::Giveaway { -1 } else { 1 };
+    let mut evaluation = Evaluation {
+        material: sign * weights.man * (men[0] - men[1]),
+        kings: sign * weights.king * (kings[0] - kings[1]),
+        back_rank: sign * weights.back_rank * (back_rank_men[0] - back_rank_men[1]),
+        mobility: sign * weights.mobility * (moves[0] - moves[1]),
+        total: 0,
+    };
+    evaluation.total = evaluation.material + evaluation.kings + evaluation.back_rank + evaluation.mobility;
+    evaluation
+}
+
+// The moves a player may choose from: pieces that can capture, and the simple
+// moves when there is nothing to capture or capturing is optional
+fn count_moves(game: &Game, simple_moves: &[SimpleMove], jump_moves: &[JumpMove]) -> i32 {
+    if jump_moves.is_empty() || !game.rules().is_capture_mandatory() {
+        (simple_moves.len() + jump_moves.len()) as i32
+    } else {
+        jump_moves.len() as i32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn game_from_fen(fen: &str, variant: GameVariant) -> Game {
+        Game::from_position(parse_fen(fen, variant).ok().unwrap(), "alice".into(), "bob".into(),
+                            TokenBalance { token_id: None, balance: 0 }, variant)
+    }
+
+    #[test]
+    fn starting_position_is_even() {
+        let game = Game::new("alice".into(), "bob".into(),
+                             TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        assert_eq!(Evaluation::default(), evaluate_position(&game, &EvalWeights::default()));
+    }
+
+    #[test]
+    fn evaluation_terms() {
+        // Black, the first player, has two men on its back rank and a king, White two men
+        let game = game_from_fen("B:W21,22:B1,2,K14", GameVariant::American);
+        let weights = EvalWeights { man: 100, king: 150, back_rank: 10, mobility: 1 };
+
+        let evaluation = evaluate_position(&game, &weights);
+        assert_eq!(0, evaluation.material);
+        assert_eq!(150, evaluation.kings);
+        assert_eq!(20, evaluation.back_rank);
+        // 2 moves for each man and 4 for the king, against 3 for the men of White
+        assert_eq!(5, evaluation.mobility);
+        assert_eq!(175, evaluation.total);
+
+        let giveaway = game_from_fen("B:W21,22:B1,2,K14", GameVariant::Giveaway);
+        assert_eq!(-175, evaluate_position(&giveaway, &weights).total);
+
+        let weights = EvalWeights { back_rank: 0, mobility: 0, ..weights };
+        assert_eq!(150, evaluate_position(&game, &weights).total);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::*;
+use crate::ai::search::legal_moves;
+use crate::pdn::move_to_pdn;
+
+/// Counts the positions reached after `depth` plies by playing every legal
+/// move, which checks the move generators against counts known to be right
+pub fn perft(game: &Game, depth: u32) -> u64 {
+    // the history of the moves isn't needed to count, and is long to copy
+    let mut root = game.clone();
+    root.moves.clear();
+    count_leaves(&root, depth)
+}
+
+/// Counts the positions reached after `depth` plies for each legal move,
+/// written in square notation, to find the move a generator gets wrong
+pub fn perft_divide(game: &Game, depth: u32) -> Vec<(String, u64)> {
+    let mut root = game.clone();
+    root.moves.clear();
+
+    legal_moves(&root).iter()
+        .map(|the_move| {
+            let child = play(&root, the_move);
+            let the_move = child.moves.last().expect("The move was recorded");
+            (move_to_pdn(root.rules(), the_move), count_leaves(&child, depth.saturating_sub(1)))
+        })
+        .collect()
+}
+
+fn count_leaves(game: &Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    legal_moves(game).iter()
+        .map(|the_move| count_leaves(&play(game, the_move), depth - 1))
+        .sum()
+}
+
+fn play(game: &Game, the_move: &SearchMove) -> Game {
+    let mut child = game.clone();
+    the_move.apply(&mut child).expect("Legal moves can be applied");
+    child
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Published perft numbers of the starting position of American checkers
+    const PERFT: [u64; 11] = [1, 7, 49, 302, 1469, 7361, 36768, 179740, 845931, 3963680, 18391564];
+
+    fn new_game() -> Game {
+        Game::new("alice".into(), "bob".into(),
+                  TokenBalance { token_id: None, balance: 0 }, GameVariant::American)
+    }
+
+    // Counts the leaves with the per-square generators of ai.rs, which the
+    // American rules left for the bitboard: men of the first player move
+    // towards increasing rows.
+    fn count_leaves_per_square(board: &Board, players: &[(Player, Direction); 2], player_index: usize,
+                               depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let (player, direction) = &players[player_index];
+        let mut jumps = Vec::new();
+        let mut simple_moves = Vec::new();
+        for row in 0..board.number_rows() {
+            for col in 0..board.number_columns() {
+                let piece_type = match board.get_tile(row, col).get_piece() {
+                    Some(piece) if piece.get_player_id() == player.id => piece.get_type(),
+                    _ => continue,
+                };
+                let (jump_move, piece_moves) = match piece_type {
+                    PieceType::Man => (find_jump_moves_for_man(board, player, *direction, row, col),
+                                       find_simple_moves_for_man(board, *direction, row, col)),
+                    PieceType::King => (find_jump_moves_for_king(board, player, row, col),
+                                        find_simple_moves_for_king(board, row, col)),
+                };
+                jumps.extend(jump_move.sequences().into_iter().filter(|sequence| sequence.len() > 1));
+                simple_moves.extend(piece_moves.iter().map(|simple_move| vec![
+                    BoardPosition::new(simple_move.from_row(), simple_move.from_column()),
+                    BoardPosition::new(simple_move.to_row(), simple_move.to_column())]));
+            }
+        }
+
+        // captures are mandatory
+        let moves = if jumps.is_empty() { simple_moves } else { jumps };
+        moves.iter()
+            .map(|positions| {
+                let child = play_on_board(board, positions, *direction);
+                count_leaves_per_square(&child, players, 1 - player_index, depth - 1)
+            })
+            .sum()
+    }
+
+    fn play_on_board(board: &Board, positions: &[BoardPosition], direction: Direction) -> Board {
+        let mut board = *board;
+        let (from, to) = (positions[0], *positions.last().unwrap());
+        board.swap_tiles(from.row, from.column, to.row, to.column);
+        for step in positions.windows(2) {
+            for captured in step[0].positions_between(&step[1]) {
+                board.clear_tile(captured.row, captured.column);
+            }
+        }
+
+        let crowning_row = match direction {
+            Direction::IncreasingRank => board.number_rows() - 1,
+            Direction::DecreasingRank => 0,
+        };
+        let is_man = board.get_tile(to.row, to.column).get_piece()
+            .map(|piece| piece.get_type() == PieceType::Man)
+            .unwrap_or(false);
+        if is_man && to.row == crowning_row {
+            board.crown(to.row, to.column);
+        }
+        board
+    }
+
+    fn perft_per_square(depth: u32) -> u64 {
+        let (player_1, player_2) = (Player { id: 1 }, Player { id: 2 });
+        let board = Board::new_checkerboard(CHECKERBOARD_SIZE, 3, &player_1, &player_2);
+        let players = [(player_1, Direction::IncreasingRank), (player_2, Direction::DecreasingRank)];
+        count_leaves_per_square(&board, &players, 0, depth)
+    }
+
+    #[test]
+    fn starting_position_per_square() {
+        for (depth, count) in PERFT.iter().enumerate().take(7) {
+            assert_eq!(*count, perft_per_square(depth as u32), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn starting_position() {
+        let game = new_game();
+        for (depth, count) in PERFT.iter().enumerate().take(8) {
+            assert_eq!(*count, perft(&game, depth as u32), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn divide() {
+        let game = new_game();
+        let mut moves: Vec<String> = perft_divide(&game, 1).into_iter()
+            .map(|(the_move, count)| {
+                assert_eq!(1, count);
+                the_move
+            })
+            .collect();
+        moves.sort();
+        assert_eq!(vec!["10-14", "10-15", "11-15", "11-16", "12-16", "9-13", "9-14"], moves);
+
+        let divide = perft_divide(&game, 5);
+        assert_eq!(PERFT[5], divide.iter().map(|(_, count)| count).sum::<u64>());
+    }
+
+    #[test]
+    fn captures_are_counted_once_per_sequence() {
+        // the king on 18 takes the four men around 10 either way round
+        let game = Game::from_position(parse_fen("B:W6,7,14,15:BK18", GameVariant::American).ok().unwrap(),
+                                       "alice".into(), "bob".into(),
+                                       TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        let mut divide = perft_divide(&game, 1);
+        divide.sort();
+        assert_eq!(vec![("18x11x2x9x18".to_string(), 1), ("18x9x2x11x18".to_string(), 1)], divide);
+        assert_eq!(2, perft(&game, 1));
+    }
+
+    // cargo test --release perft_deeper -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn perft_deeper() {
+        let game = new_game();
+        for (depth, count) in PERFT.iter().enumerate().skip(8) {
+            let start = std::time::Instant::now();
+            assert_eq!(*count, perft(&game, depth as u32), "depth {}", depth);
+            println!("perft({}) = {} in {:?}", depth, count, start.elapsed());
+
+            let start = std::time::Instant::now();
+            assert_eq!(*count, perft_per_square(depth as u32), "depth {} per square", depth);
+            println!("perft({}) per square in {:?}", depth, start.elapsed());
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use std::rc::Rc;
+
+use crate::*;
+
+/// Score of a won position, less the number of plies needed to win it
+pub const WIN_SCORE: i32 = 1_000_000;
+
+// Scores beyond this one are wins or losses found by the search
+const WIN_THRESHOLD: i32 = WIN_SCORE - 1_000;
+
+// Deepest ply the quiescence search goes to, captures end long before
+const MAX_PLY: usize = 256;
+
+// Positions visited between two calls asking whether the search must stop, a power of 2
+const STOP_CHECK_INTERVAL: u64 = 16;
+
+// Seed of the Zobrist keys, fixed so that hashes and searches are the same on every run
+const ZOBRIST_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// How far a search may go: it stops after `max_depth` plies, or once it has
+/// visited `max_nodes` positions and keeps the last depth it completed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_depth: u32,
+    pub max_nodes: u64,
+}
+
+/// A move of the player to move, its board positions as `make_move` reads them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMove {
+    pub positions: Vec<BoardPosition>,
+    pub is_capture: bool,
+}
+
+impl SearchMove {
+    pub fn apply(&self, game: &mut Game) -> Result<GameState, MoveError> {
+        if self.is_capture {
+            game.apply_jump_move(self.positions.clone())
+        } else {
+            let (from, to) = (self.positions[0], self.positions[1]);
+            game.apply_simple_move(SimpleMove::new(from.row, from.column, to.row, to.column))
+        }
+    }
+}
+
+/// The move a search settled on, `None` when the game is over. The score is
+/// seen from the player to move, `WIN_SCORE - n` for a win in `n` plies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<SearchMove>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone)]
+struct TableEntry {
+    hash: u64,
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best_move: Option<SearchMove>,
+}
+
+/// Alpha-beta search with iterative deepening over the moves of a `Game`.
+/// Positions already searched are kept in a transposition table, keyed by
+/// their Zobrist hash, and reused by the next searches.
+pub struct Search {
+    weights: EvalWeights,
+    table: Vec<Option<TableEntry>>,
+    // how often a move from a square to another caused a cutoff
+    history: Vec<u32>,
+    // best move at the root in the last iteration
+    root_move: Option<SearchMove>,
+    nodes: u64,
+    max_nodes: u64,
+    stopped: bool,
+    // asked every few positions whether the search must stop before its limits
+    should_stop: Option<fn() -> bool>,
+    // exact results of the positions with few pieces left
+    tablebase: Option<Rc<Tablebase>>,
+}
+
+impl Search {
+    /// Creates a search with a transposition table of `2^table_bits` entries
+    pub fn new(weights: EvalWeights, table_bits: u32) -> Search {
+        Search {
+            weights,
+            table: vec![None; 1 << table_bits],
+            history: Vec::new(),
+            root_move: None,
+            nodes: 0,
+            max_nodes: 0,
+            stopped: false,
+            should_stop: None,
+            tablebase: None,
+        }
+    }
+
+    /// Stops the search, keeping the last depth it completed, once
+    /// `should_stop` returns true, such as when the gas of the call runs low
+    pub fn with_stop(mut self, should_stop: fn() -> bool) -> Search {
+        self.should_stop = Some(should_stop);
+        self
+    }
+
+    /// Plays the positions held by the tablebase perfectly, without searching them
+    pub fn with_tablebase(mut self, tablebase: Rc<Tablebase>) -> Search {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    pub fn best_move(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        let squares = game.board.number_rows() * game.board.number_columns();
+        self.history = vec![0; squares * squares];
+        self.nodes = 0;
+        self.max_nodes = limits.max_nodes;
+        self.stopped = false;
+
+        // every node copies its parent, so the root keeps only what the search needs
+        let root = game.search_copy();
+
+        let mut result = SearchResult { best_move: None, score: 0, depth: 0, nodes: 0 };
+        let moves = legal_moves(&root);
+        if root.is_finished() || moves.is_empty() {
+            return result;
+        }
+
+        if let Some(probe) = self.probe_tablebase(&root) {
+            result.score = tablebase_score(&probe, 0);
+            result.best_move = probe.best_move;
+            return result;
+        }
+
+        result.score = self.evaluate(&root);
+        // kept if the budget runs out before the first depth is searched
+        result.best_move = moves.first().cloned();
+
+        for depth in 1..=limits.max_depth {
+            let score = self.negamax(&root, depth, 0, -WIN_SCORE, WIN_SCORE);
+            if self.stopped {
+                break;
+            }
+
+            result.score = score;
+            result.depth = depth;
+            result.best_move = self.root_move.take();
+            if score.abs() > WIN_THRESHOLD {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn negamax(&mut self, game: &Game, depth: u32, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        if self.visit() {
+            return 0;
+        }
+        if let Some(probe) = self.probe_tablebase(game) {
+            return tablebase_score(&probe, ply);
+        }
+
+        let hash = zobrist_hash(game);
+        let table_move = match self.probe(hash) {
+            Some(entry) => {
+                if ply > 0 && entry.depth >= depth {
+                    let score = score_from_table(entry.score, ply);
+                    let cutoff = match entry.bound {
+                        Bound::Exact => true,
+                        Bound::Lower => score >= beta,
+                        Bound::Upper => score <= alpha,
+                    };
+                    if cutoff {
+                        return score;
+                    }
+                }
+                entry.best_move.clone()
+            }
+            None => None,
+        };
+
+        let mut moves = legal_moves(game);
+        if moves.is_empty() {
+            return self.evaluate(game);
+        }
+        self.order_moves(game, &mut moves, table_move.as_ref());
+
+        let original_alpha = alpha;
+        let mut best_score = -WIN_SCORE;
+        let mut best_move = None;
+        for the_move in moves {
+            let mut child = game.clone();
+            let game_state = the_move.apply(&mut child).expect("Legal moves can be applied");
+            let score = match terminal_score(game, &game_state, ply) {
+                Some(score) => score,
+                None => -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha),
+            };
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(the_move);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                if let Some(the_move) = &best_move {
+                    if !the_move.is_capture {
+                        let index = self.history_index(game, the_move);
+                        self.history[index] += depth * depth;
+                    }
+                }
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        if ply == 0 {
+            self.root_move = best_move.clone();
+        }
+        self.store(TableEntry { hash, depth, score: score_to_table(best_score, ply), bound, best_move });
+
+        best_score
+    }
+
+    // Plays the captures until the position is quiet, so that the evaluation
+    // doesn't stop in the middle of an exchange
+    fn quiescence(&mut self, game: &Game, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.visit() {
+            return 0;
+        }
+
+        if game.available_jump_moves.is_empty() || ply >= MAX_PLY {
+            return self.evaluate(game);
+        }
+
+        // the player may decline a capture only where captures aren't mandatory
+        let mut best_score = -WIN_SCORE;
+        if !game.rules().is_capture_mandatory() {
+            best_score = self.evaluate(game);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+        }
+
+        let mut captures: Vec<SearchMove> = legal_moves(game).into_iter()
+            .filter(|the_move| the_move.is_capture)
+            .collect();
+        self.order_moves(game, &mut captures, None);
+
+        for the_move in captures {
+            let mut child = game.clone();
+            let game_state = the_move.apply(&mut child).expect("Legal moves can be applied");
+            let score = match terminal_score(game, &game_state, ply) {
+                Some(score) => score,
+                None => -self.quiescence(&child, ply + 1, -beta, -alpha),
+            };
+            if self.stopped {
+                return 0;
+            }
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    /// The static evaluation of the position, from the side of the player to move
+    pub fn evaluate(&self, game: &Game) -> i32 {
+        let total = evaluate_position(game, &self.weights).total;
+        if game.current_player_index == 0 { total } else { -total }
+    }
+
+    fn probe_tablebase(&self, game: &Game) -> Option<TablebaseProbe> {
+        self.tablebase.as_ref().and_then(|tablebase| tablebase.probe_game(game))
+    }
+
+    // Counts a visited position, and stops the search once the budget is spent
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            self.stopped = true;
+        }
+        if self.nodes & (STOP_CHECK_INTERVAL - 1) == 0 && matches!(self.should_stop, Some(should_stop) if should_stop()) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    // Searches the move of the transposition table first, then the captures
+    // taking the most pieces, the promotions and the moves of the history table
+    fn order_moves(&self, game: &Game, moves: &mut [SearchMove], table_move: Option<&SearchMove>) {
+        let player_info = &game.players[game.current_player_index];
+        let key = |the_move: &SearchMove| -> u64 {
+            if Some(the_move) == table_move {
+                return 0;
+            }
+            let from = the_move.positions[0];
+            let to = the_move.positions[the_move.positions.len() - 1];
+            let is_man = game.board.get_tile(from.row, from.column).get_piece()
+                .map(|piece| piece.get_type() == PieceType::Man)
+                .unwrap_or(false);
+            let promotes = is_man && game.rules().is_promotion_row(&game.board, player_info.direction, to.row);
+
+            let mut priority: u64 = 0;
+            if the_move.is_capture {
+                priority += 1_000_000 + 10_000 * the_move.positions.len() as u64;
+            }
+            if promotes {
+                priority += 100_000;
+            }
+            priority += self.history[self.history_index(game, the_move)].min(99_999) as u64;
+            u64::MAX - priority
+        };
+
+        // a stable sort keeps the order of the generator between equal moves
+        moves.sort_by_cached_key(key);
+    }
+
+    fn history_index(&self, game: &Game, the_move: &SearchMove) -> usize {
+        let squares = game.board.number_rows() * game.board.number_columns();
+        let square = |position: &BoardPosition| position.row * game.board.number_columns() + position.column;
+        square(&the_move.positions[0]) * squares + square(&the_move.positions[the_move.positions.len() - 1])
+    }
+
+    fn probe(&self, hash: u64) -> Option<&TableEntry> {
+        self.table[(hash as usize) & (self.table.len() - 1)].as_ref()
+            .filter(|entry| entry.hash == hash)
+    }
+
+    fn store(&mut self, entry: TableEntry) {
+        let index = (entry.hash as usize) & (self.table.len() - 1);
+        self.table[index] = Some(entry);
+    }
+}
+
+/// Searches the best move of the player to move with the default weights
+pub fn find_best_move(game: &Game, limits: SearchLimits) -> SearchResult {
+    Search::new(EvalWeights::default(), 16).best_move(game, limits)
+}
+
+/// The moves the player to move may make, each complete capture sequence
+/// being a move of its own
+pub fn legal_moves(game: &Game) -> Vec<SearchMove> {
+    let mut moves: Vec<SearchMove> = game.available_jump_moves.iter()
+        .flat_map(|jump_move| jump_move.sequences())
+        .map(|positions| SearchMove { positions, is_capture: true })
+        .collect();
+
+    if moves.is_empty() || !game.rules().is_capture_mandatory() {
+        moves.extend(game.available_simple_moves.iter().map(|simple_move| SearchMove {
+            positions: vec![
+                BoardPosition::new(simple_move.from_row(), simple_move.from_column()),
+                BoardPosition::new(simple_move.to_row(), simple_move.to_column())],
+            is_capture: false,
+        }));
+    }
+
+    moves
+}
+
+/// Hashes the board and the player to move: every kind of piece on every
+/// square has a random key, the keys of the pieces on the board are xored
+pub fn zobrist_hash(game: &Game) -> u64 {
+    let board = &game.board;
+    let mut hash = if game.current_player_index == 1 { zobrist_key(0) } else { 0 };
+    for row in 0..board.number_rows() {
+        for column in 0..board.number_columns() {
+            if let Some(piece) = board.get_tile(row, column).get_piece() {
+                let kind = (piece.get_player_id() as u64 - 1) * 2
+                    + if piece.get_type() == PieceType::King { 1 } else { 0 };
+                let square = (row * board.number_columns() + column) as u64;
+                hash ^= zobrist_key(1 + square * 4 + kind);
+            }
+        }
+    }
+    hash
+}
+
+// The random key with the given index, from the splitmix64 generator
+fn zobrist_key(index: u64) -> u64 {
+    let mut key = ZOBRIST_SEED.wrapping_add(index.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    key ^ (key >> 31)
+}
+
+// The score of a move that ends the game, seen from the player who made it
+fn terminal_score(game: &Game, game_state: &GameState, ply: usize) -> Option<i32> {
+    match game_state {
+        GameState::InProgress => None,
+        GameState::Draw => Some(0),
+        GameState::GameOver { winner_id } => {
+            let score = WIN_SCORE - (ply as i32 + 1);
+            Some(if *winner_id == game.current_player_index { score } else { -score })
+        }
+    }
+}
+
+// The score of a position found in the tablebase, `ply` plies from the root
+fn tablebase_score(probe: &TablebaseProbe, ply: usize) -> i32 {
+    let plies = (ply + probe.plies as usize) as i32;
+    match probe.result {
+        TablebaseResult::Win => WIN_SCORE - plies,
+        TablebaseResult::Loss => plies - WIN_SCORE,
+        TablebaseResult::Draw => 0,
+    }
+}
+
+// Wins are stored relative to the position, and read back relative to the root
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_THRESHOLD {
+        score + ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_THRESHOLD {
+        score - ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limits(max_depth: u32) -> SearchLimits {
+        SearchLimits { max_depth, max_nodes: 1_000_000 }
+    }
+
+    fn game_from_fen(fen: &str) -> Game {
+        Game::from_position(parse_fen(fen, GameVariant::American).ok().unwrap(), "alice".into(), "bob".into(),
+                            TokenBalance { token_id: None, balance: 0 }, GameVariant::American)
+    }
+
+    #[test]
+    fn takes_the_last_piece() {
+        let game = Game::with_piece_positions(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(0, 6)],
+            vec![BoardPosition::new(3, 3)]);
+
+        let result = find_best_move(&game, limits(4));
+        assert_eq!(Some(SearchMove {
+            positions: vec![BoardPosition::new(2, 2), BoardPosition::new(4, 4)],
+            is_capture: true,
+        }), result.best_move);
+        assert_eq!(WIN_SCORE - 1, result.score);
+    }
+
+    #[test]
+    fn does_not_give_a_piece_away() {
+        // b2 may go to a3, safe, or to c3 where d4 takes it
+        let game = Game::with_piece_positions(
+            vec![BoardPosition::new(1, 1)],
+            vec![BoardPosition::new(3, 3), BoardPosition::new(7, 7)]);
+
+        let result = find_best_move(&game, limits(3));
+        assert_eq!(Some(SearchMove {
+            positions: vec![BoardPosition::new(1, 1), BoardPosition::new(2, 0)],
+            is_capture: false,
+        }), result.best_move);
+    }
+
+    #[test]
+    fn sees_the_capture_at_the_horizon() {
+        // at depth 1, 1-6 only looks as good as 1-5 without the quiescence search: 10x1 takes it
+        let game = game_from_fen("B:W10:B1,12");
+        let material = EvalWeights { back_rank: 0, mobility: 0, ..EvalWeights::default() };
+
+        let result = Search::new(material, 16).best_move(&game, limits(1));
+        let square = |number| AmericanRules.square_position(number).unwrap();
+        assert_eq!(Some(SearchMove { positions: vec![square(1), square(5)], is_capture: false }),
+                   result.best_move);
+        assert_eq!(100, result.score);
+        assert_eq!(1, result.depth);
+    }
+
+    #[test]
+    fn searches_are_deterministic_and_within_budget() {
+        let game = Game::new("alice".into(), "bob".into(),
+                             TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+
+        let limits = SearchLimits { max_depth: 20, max_nodes: 5_000 };
+        let result = find_best_move(&game, limits);
+        assert_eq!(result, find_best_move(&game, limits));
+        assert!(result.nodes <= 5_001);
+        assert!(result.depth >= 3);
+
+        let mut the_game = game.clone();
+        assert!(result.best_move.unwrap().apply(&mut the_game).is_ok());
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let game = Game::new("alice".into(), "bob".into(),
+                             TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+
+        let result = Search::new(EvalWeights::default(), 10).with_stop(|| true)
+            .best_move(&game, SearchLimits { max_depth: 20, max_nodes: 5_000 });
+        assert_eq!(STOP_CHECK_INTERVAL, result.nodes);
+        assert_eq!(1, result.depth);
+        assert!(result.best_move.unwrap().apply(&mut game.clone()).is_ok());
+    }
+
+    #[test]
+    fn search_copy_keeps_only_the_position() {
+        let mut game = Game::new("alice".into(), "bob".into(),
+                                 TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        for _ in 0..4 {
+            game.save_takeback_position();
+            let result = find_best_move(&game, SearchLimits { max_depth: 2, max_nodes: 1_000 });
+            result.best_move.unwrap().apply(&mut game).unwrap();
+        }
+
+        let copy = game.search_copy();
+        assert!(copy.moves.is_empty() && copy.takeback_positions.is_empty());
+        assert_eq!(game.position_history, copy.position_history);
+        assert_eq!(zobrist_hash(&game), zobrist_hash(&copy));
+        let limits = SearchLimits { max_depth: 4, max_nodes: 2_000 };
+        assert_eq!(find_best_move(&game, limits), find_best_move(&copy, limits));
+    }
+
+    #[test]
+    fn moves_in_every_variant() {
+        for variant in [GameVariant::American, GameVariant::International, GameVariant::Russian,
+                        GameVariant::Italian, GameVariant::Turkish, GameVariant::Giveaway] {
+            let mut game = Game::new("alice".into(), "bob".into(),
+                                     TokenBalance { token_id: None, balance: 0 }, variant);
+            for _ in 0..6 {
+                let result = find_best_move(&game, SearchLimits { max_depth: 3, max_nodes: 20_000 });
+                result.best_move.unwrap().apply(&mut game).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn zobrist_hash_of_positions() {
+        let game = game_from_fen("B:W22:B11");
+        let other_player = game_from_fen("W:W22:B11");
+        let king = game_from_fen("B:W22:BK11");
+
+        let hashes = [zobrist_hash(&game), zobrist_hash(&other_player), zobrist_hash(&king)];
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_eq!(hashes[0], zobrist_hash(&game.clone()));
+    }
+}
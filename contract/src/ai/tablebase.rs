@@ -0,0 +1,828 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::*;
+use crate::ai::bitboard::BitBoard;
+use crate::player::Player;
+
+// Tablebase files start with these bytes, followed by the version of the format
+const MAGIC: &[u8; 4] = b"CKTB";
+const FORMAT_VERSION: u8 = 2;
+
+// Dark squares of the 8x8 board, the only ones pieces stand on
+const SQUARES: usize = 32;
+const DARK_SQUARES: u64 = 0xaa55_aa55_aa55_aa55;
+
+// Dark squares, numbered from 0 as in draughts notation, where the men of
+// each player stand: all but the row they are crowned on
+const MAN_SQUARES: [u32; 2] = [0x0fff_ffff, 0xffff_fff0];
+// The back row of the first player, where the men of the second player never stand
+const BACK_ROW: u32 = 0xf;
+// Where the men of both players stand
+const SHARED_SQUARES: u32 = MAN_SQUARES[0] & MAN_SQUARES[1];
+const MAN_SQUARE_COUNT: usize = MAN_SQUARES[1].count_ones() as usize;
+const BACK_ROW_COUNT: usize = BACK_ROW.count_ones() as usize;
+const SHARED_SQUARE_COUNT: usize = SHARED_SQUARES.count_ones() as usize;
+
+// Rows where the men of the first and of the second player are crowned
+const PROMOTION_ROWS: [u64; 2] = [0xff << 56, 0xff];
+
+const DIRECTIONS: [Direction; 2] = [Direction::IncreasingRank, Direction::DecreasingRank];
+
+// A position being solved for which no win was found yet
+const NO_WIN: u16 = u16::MAX;
+
+/// How a position ends with perfect play, for the player to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TablebaseResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// What a tablebase knows of a position: its result, the number of plies
+/// left until the game is over when it's won or lost, and a move that keeps
+/// the result. The winner takes the shortest way, the loser the longest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TablebaseProbe {
+    pub result: TablebaseResult,
+    pub plies: u32,
+    pub best_move: Option<SearchMove>,
+}
+
+/// Results of every position with a few pieces on the board, found by
+/// retrograde analysis: from the positions where the game is over, back to
+/// the ones leading to them. Only American checkers and giveaway checkers,
+/// which share the moves of the 8x8 board, are supported. The draw after
+/// moves without progress isn't taken into account.
+pub struct Tablebase {
+    variant: GameVariant,
+    max_pieces: usize,
+    // one byte per position of each material: 0 for a draw, otherwise
+    // 1 + the number of plies left until the game is over. Positions are
+    // indexed so that each index is a legal placement of the pieces.
+    slices: BTreeMap<Material, Vec<u8>>,
+}
+
+impl Tablebase {
+    /// Solves every position with up to `max_pieces` pieces. Positions with
+    /// fewer pieces, or fewer men, are solved first: captures and crownings
+    /// lead to them.
+    pub fn generate(variant: GameVariant, max_pieces: usize) -> Tablebase {
+        assert!(matches!(variant, GameVariant::American | GameVariant::Giveaway),
+                "Tablebases are only generated for American and giveaway checkers");
+
+        let mut tablebase = Tablebase { variant, max_pieces, slices: BTreeMap::new() };
+        for material in materials(max_pieces) {
+            let values = tablebase.solve(material);
+            tablebase.slices.insert(material, values);
+        }
+        tablebase
+    }
+
+    pub fn variant(&self) -> GameVariant {
+        self.variant
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Looks up a position, `None` when it has more pieces than the tablebase holds
+    pub fn probe(&self, board: &Board, current_player_index: usize) -> Option<TablebaseProbe> {
+        if board.number_rows() != CHECKERBOARD_SIZE || board.number_columns() != CHECKERBOARD_SIZE {
+            return None;
+        }
+
+        let position = Position::from_board(board, current_player_index);
+        if !position.is_valid() || position.pieces[1 - current_player_index] == 0 {
+            return None;
+        }
+        let value = self.value(&position)?;
+
+        // the next position is one ply closer to the end, or drawn as well
+        let best_move = position.moves().into_iter()
+            .find(|the_move| self.value(&position.play(the_move)) == Some(value.saturating_sub(1)));
+
+        let (result, plies) = match value {
+            0 => (TablebaseResult::Draw, 0),
+            value => {
+                let plies = value as usize - 1;
+                (if self.wins(plies) { TablebaseResult::Win } else { TablebaseResult::Loss }, plies as u32)
+            }
+        };
+        Some(TablebaseProbe { result, plies, best_move })
+    }
+
+    /// Looks up the position of a game in progress
+    pub fn probe_game(&self, game: &Game) -> Option<TablebaseProbe> {
+        if game.variant != self.variant || game.is_finished() {
+            return None;
+        }
+        self.probe(&game.board, game.current_player_index)
+    }
+
+    /// Writes the tablebase: a header with the variant and the number of
+    /// pieces, then each material with one byte per position
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION, variant_code(self.variant), self.max_pieces as u8])?;
+        writer.write_all(&(self.slices.len() as u32).to_le_bytes())?;
+        for (material, values) in &self.slices {
+            writer.write_all(&[material.men[0], material.men[1], material.kings[0], material.kings[1]])?;
+            writer.write_all(&(values.len() as u32).to_le_bytes())?;
+            writer.write_all(values)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a tablebase written by `write`
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Tablebase> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut header = [0; 11];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != FORMAT_VERSION {
+            return Err(invalid("Not a tablebase file of this version"));
+        }
+        let variant = match header[5] {
+            0 => GameVariant::American,
+            1 => GameVariant::Giveaway,
+            _ => return Err(invalid("Unknown variant")),
+        };
+        let max_pieces = header[6] as usize;
+        let slice_count = u32::from_le_bytes([header[7], header[8], header[9], header[10]]);
+
+        let mut slices = BTreeMap::new();
+        for _ in 0..slice_count {
+            let mut slice_header = [0; 8];
+            reader.read_exact(&mut slice_header)?;
+            let material = Material {
+                men: [slice_header[0], slice_header[1]],
+                kings: [slice_header[2], slice_header[3]],
+            };
+            let len = u32::from_le_bytes([slice_header[4], slice_header[5], slice_header[6], slice_header[7]]);
+            if material.pieces() > max_pieces || len as usize != 2 * material.placements() {
+                return Err(invalid("Invalid material"));
+            }
+
+            let mut values = vec![0; len as usize];
+            reader.read_exact(&mut values)?;
+            slices.insert(material, values);
+        }
+
+        Ok(Tablebase { variant, max_pieces, slices })
+    }
+
+    // Solves the positions of a material, once the materials they lead to are solved.
+    // Positions are decided in the order of the plies left until the game is over:
+    // a position is won as soon as one of its moves leads to a lost position, and
+    // lost once all of its moves lead to won positions. The positions never decided
+    // are drawn.
+    fn solve(&self, material: Material) -> Vec<u8> {
+        let len = 2 * material.placements();
+        let mut values = vec![0u8; len];
+        // moves to positions of this material not known to be won yet, and moves to drawn positions
+        let mut remaining = vec![0u8; len];
+        let mut shortest_win = vec![NO_WIN; len];
+        let mut longest_loss = vec![0u16; len];
+        let mut by_plies: Vec<Vec<u32>> = Vec::new();
+
+        for index in 0..len {
+            let position = Position::from_index(material, index);
+            let moves = position.moves();
+            if moves.is_empty() {
+                push(&mut by_plies, 0, index);
+                continue;
+            }
+
+            for the_move in &moves {
+                let next = position.play(the_move);
+                if next.material() == material {
+                    remaining[index] += 1;
+                    continue;
+                }
+                match self.value(&next).expect("Captures and crownings lead to materials solved first") {
+                    // a draw is never decided, the position can't be lost anymore
+                    0 => remaining[index] += 1,
+                    value => {
+                        let plies = value as u16;
+                        if self.wins(value as usize - 1) {
+                            longest_loss[index] = longest_loss[index].max(plies);
+                        } else {
+                            shortest_win[index] = shortest_win[index].min(plies);
+                        }
+                    }
+                }
+            }
+
+            if shortest_win[index] != NO_WIN {
+                push(&mut by_plies, shortest_win[index] as usize, index);
+            } else if remaining[index] == 0 {
+                push(&mut by_plies, longest_loss[index] as usize, index);
+            }
+        }
+
+        let mut plies = 0;
+        while plies < by_plies.len() {
+            for index in std::mem::take(&mut by_plies[plies]) {
+                let index = index as usize;
+                if values[index] != 0 {
+                    continue;
+                }
+                assert!(plies < u8::MAX as usize, "Games longer than {} plies don't fit the tablebase format", u8::MAX - 1);
+                values[index] = plies as u8 + 1;
+
+                let is_lost = !self.wins(plies);
+                for previous in Position::from_index(material, index).unmoves() {
+                    let previous_index = previous.index();
+                    if values[previous_index] != 0 {
+                        continue;
+                    }
+
+                    let next_plies = plies as u16 + 1;
+                    if is_lost {
+                        if next_plies < shortest_win[previous_index] {
+                            shortest_win[previous_index] = next_plies;
+                            push(&mut by_plies, next_plies as usize, previous_index);
+                        }
+                    } else {
+                        remaining[previous_index] -= 1;
+                        longest_loss[previous_index] = longest_loss[previous_index].max(next_plies);
+                        if remaining[previous_index] == 0 && shortest_win[previous_index] == NO_WIN {
+                            push(&mut by_plies, longest_loss[previous_index] as usize, previous_index);
+                        }
+                    }
+                }
+            }
+            plies += 1;
+        }
+
+        values
+    }
+
+    // The value of a position in the format of the tablebase
+    fn value(&self, position: &Position) -> Option<u8> {
+        // the player without pieces has no move left, the game is over
+        if position.pieces[position.to_move] == 0 {
+            return Some(1);
+        }
+        self.slices.get(&position.material()).map(|values| values[position.index()])
+    }
+
+    // Whether the player to move wins when the game is over after `plies`.
+    // The player left without moves loses, or wins in giveaway checkers.
+    fn wins(&self, plies: usize) -> bool {
+        (plies % 2 == 1) != (self.variant == GameVariant::Giveaway)
+    }
+}
+
+// Queues a position to be decided once the positions closer to the end are
+fn push(by_plies: &mut Vec<Vec<u32>>, plies: usize, index: usize) {
+    if by_plies.len() <= plies {
+        by_plies.resize(plies + 1, Vec::new());
+    }
+    by_plies[plies].push(index as u32);
+}
+
+fn variant_code(variant: GameVariant) -> u8 {
+    match variant {
+        GameVariant::Giveaway => 1,
+        _ => 0,
+    }
+}
+
+// Every material with up to `max_pieces` pieces and at least one piece for
+// each player, in the order they are solved in
+fn materials(max_pieces: usize) -> Vec<Material> {
+    let mut materials = Vec::new();
+    for men_1 in 0..=max_pieces {
+        for men_2 in 0..=max_pieces - men_1 {
+            for kings_1 in 0..=max_pieces - men_1 - men_2 {
+                for kings_2 in 0..=max_pieces - men_1 - men_2 - kings_1 {
+                    if men_1 + kings_1 > 0 && men_2 + kings_2 > 0 {
+                        materials.push(Material {
+                            men: [men_1 as u8, men_2 as u8],
+                            kings: [kings_1 as u8, kings_2 as u8],
+                        });
+                    }
+                }
+            }
+        }
+    }
+    materials.sort_by_key(|material| (material.pieces(), material.men[0] + material.men[1]));
+    materials
+}
+
+// The men and kings of each player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Material {
+    men: [u8; 2],
+    kings: [u8; 2],
+}
+
+impl Material {
+    fn pieces(&self) -> usize {
+        self.groups().iter().sum()
+    }
+
+    // Pieces are placed on the board in this order: the men of each player, then the kings
+    fn groups(&self) -> [usize; 4] {
+        [self.men[0] as usize, self.men[1] as usize, self.kings[0] as usize, self.kings[1] as usize]
+    }
+
+    // Ways to place the pieces on the board, with one of the players to move
+    fn placements(&self) -> usize {
+        self.men_placements() * self.king_placements()
+    }
+
+    // Ways to place the men of both players on the squares they stand on
+    fn men_placements(&self) -> usize {
+        (0..=(self.men[0] as usize).min(BACK_ROW_COUNT)).map(|back_row_men| self.men_placements_with(back_row_men)).sum()
+    }
+
+    // Ways to place the men when `back_row_men` men of the first player stand
+    // on its back row: the others share their squares with the men of the
+    // second player
+    fn men_placements_with(&self, back_row_men: usize) -> usize {
+        let shared_men = self.men[0] as usize - back_row_men;
+        binomial(BACK_ROW_COUNT, back_row_men)
+            * binomial(SHARED_SQUARE_COUNT, shared_men)
+            * binomial(MAN_SQUARE_COUNT - shared_men, self.men[1] as usize)
+    }
+
+    // Ways to place the kings on the squares left by the men
+    fn king_placements(&self) -> usize {
+        let free = SQUARES - self.men[0] as usize - self.men[1] as usize;
+        binomial(free, self.kings[0] as usize) * binomial(free - self.kings[0] as usize, self.kings[1] as usize)
+    }
+}
+
+// Squares of the pieces of both players, as in a bitboard, and the player to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Position {
+    pieces: [u64; 2],
+    kings: u64,
+    to_move: usize,
+}
+
+impl Position {
+    fn from_board(board: &Board, to_move: usize) -> Position {
+        let mut position = Position { pieces: [0, 0], kings: 0, to_move };
+        for row in 0..CHECKERBOARD_SIZE {
+            for column in 0..CHECKERBOARD_SIZE {
+                if let Some(piece) = board.get_tile(row, column).get_piece() {
+                    let square = 1 << (row * CHECKERBOARD_SIZE + column);
+                    position.pieces[piece.get_player_id() as usize - 1] |= square;
+                    if piece.get_type() == PieceType::King {
+                        position.kings |= square;
+                    }
+                }
+            }
+        }
+        position
+    }
+
+    // Places the pieces of the material as the index of the position tells.
+    // The men come first: the positions with fewer men of the first player on
+    // its back row, then the men of the first player on its back row, on the
+    // shared squares and the men of the second player on the squares left.
+    // The kings take the squares left by the men. Each group of pieces takes
+    // a combination of its squares, ranked in the combinatorial number system.
+    fn from_index(material: Material, index: usize) -> Position {
+        let [men_1, men_2, kings_1, kings_2] = material.groups();
+        let placements = material.placements();
+        let (to_move, index) = (index / placements, index % placements);
+        let king_placements = material.king_placements();
+        let (mut men_index, king_index) = (index / king_placements, index % king_placements);
+
+        let mut back_row_men = 0;
+        while men_index >= material.men_placements_with(back_row_men) {
+            men_index -= material.men_placements_with(back_row_men);
+            back_row_men += 1;
+        }
+        let shared_men = men_1 - back_row_men;
+        let men_2_placements = binomial(MAN_SQUARE_COUNT - shared_men, men_2);
+        let shared_placements = binomial(SHARED_SQUARE_COUNT, shared_men);
+
+        let back_row = unrank(men_index / men_2_placements / shared_placements, back_row_men, !BACK_ROW);
+        let shared = unrank(men_index / men_2_placements % shared_placements, shared_men, !SHARED_SQUARES);
+        let men_1_squares = back_row | shared;
+        let men = [men_1_squares, unrank(men_index % men_2_placements, men_2, men_1_squares | !MAN_SQUARES[1])];
+
+        let used = men[0] | men[1];
+        let kings_2_placements = binomial(SQUARES - men_1 - men_2 - kings_1, kings_2);
+        let kings_1_squares = unrank(king_index / kings_2_placements, kings_1, used);
+        let kings = [kings_1_squares, unrank(king_index % kings_2_placements, kings_2, used | kings_1_squares)];
+
+        Position {
+            pieces: [board_mask(men[0] | kings[0]), board_mask(men[1] | kings[1])],
+            kings: board_mask(kings[0] | kings[1]),
+            to_move,
+        }
+    }
+
+    fn index(&self) -> usize {
+        let material = self.material();
+        let [men_1, men_2, kings_1, kings_2] = material.groups();
+        let men = [dark_mask(self.pieces[0] & !self.kings), dark_mask(self.pieces[1] & !self.kings)];
+        let kings = [dark_mask(self.pieces[0] & self.kings), dark_mask(self.pieces[1] & self.kings)];
+
+        let back_row_men = (men[0] & BACK_ROW).count_ones() as usize;
+        let shared_men = men_1 - back_row_men;
+        let mut men_index = rank(men[0] & BACK_ROW, !BACK_ROW);
+        men_index = men_index * binomial(SHARED_SQUARE_COUNT, shared_men) + rank(men[0] & SHARED_SQUARES, !SHARED_SQUARES);
+        men_index = men_index * binomial(MAN_SQUARE_COUNT - shared_men, men_2) + rank(men[1], men[0] | !MAN_SQUARES[1]);
+        men_index += (0..back_row_men).map(|fewer| material.men_placements_with(fewer)).sum::<usize>();
+
+        let used = men[0] | men[1];
+        let king_index = rank(kings[0], used) * binomial(SQUARES - men_1 - men_2 - kings_1, kings_2)
+            + rank(kings[1], used | kings[0]);
+
+        self.to_move * material.placements() + men_index * material.king_placements() + king_index
+    }
+
+    fn material(&self) -> Material {
+        let count = |mask: u64| mask.count_ones() as u8;
+        Material {
+            men: [count(self.pieces[0] & !self.kings), count(self.pieces[1] & !self.kings)],
+            kings: [count(self.pieces[0] & self.kings), count(self.pieces[1] & self.kings)],
+        }
+    }
+
+    // Pieces stand on dark squares, and men never on the row they are crowned on
+    fn is_valid(&self) -> bool {
+        (self.pieces[0] | self.pieces[1]) & !DARK_SQUARES == 0
+            && (0..2).all(|index| self.pieces[index] & !self.kings & PROMOTION_ROWS[index] == 0)
+    }
+
+    // Captures are mandatory in the variants of the tablebase
+    fn moves(&self) -> Vec<SearchMove> {
+        let player = Player { id: self.to_move as u32 + 1 };
+        let bit_board = BitBoard::from_masks(self.pieces, self.kings);
+
+        let jump_moves = bit_board.find_jump_moves(&player, DIRECTIONS[self.to_move]);
+        if !jump_moves.is_empty() {
+            return jump_moves.iter()
+                .flat_map(|jump_move| jump_move.sequences())
+                .map(|positions| SearchMove { positions, is_capture: true })
+                .collect();
+        }
+
+        bit_board.find_simple_moves(&player, DIRECTIONS[self.to_move]).iter()
+            .map(|simple_move| SearchMove {
+                positions: vec![
+                    BoardPosition::new(simple_move.from_row(), simple_move.from_column()),
+                    BoardPosition::new(simple_move.to_row(), simple_move.to_column())],
+                is_capture: false,
+            })
+            .collect()
+    }
+
+    fn play(&self, the_move: &SearchMove) -> Position {
+        let (player, opponent) = (self.to_move, 1 - self.to_move);
+        let from = position_mask(the_move.positions[0]);
+        let to = position_mask(*the_move.positions.last().unwrap());
+        let mut next = *self;
+
+        if the_move.is_capture {
+            for jump in the_move.positions.windows(2) {
+                let captured = position_mask(BoardPosition::new(
+                    (jump[0].row + jump[1].row) / 2, (jump[0].column + jump[1].column) / 2));
+                next.pieces[opponent] &= !captured;
+                next.kings &= !captured;
+            }
+        }
+
+        let is_king = self.kings & from != 0;
+        next.pieces[player] = (next.pieces[player] & !from) | to;
+        next.kings &= !from;
+        if is_king || to & PROMOTION_ROWS[player] != 0 {
+            next.kings |= to;
+        }
+        next.to_move = opponent;
+        next
+    }
+
+    // The positions of the same material this one is reached from: the player
+    // who just moved made a simple move, and had nothing to capture
+    fn unmoves(&self) -> Vec<Position> {
+        let player = 1 - self.to_move;
+        let occupied = self.pieces[0] | self.pieces[1];
+        let size = CHECKERBOARD_SIZE as isize;
+        // men came from the row behind them
+        let man_row_step = if player == 0 { -1 } else { 1 };
+
+        let mut previous_positions = Vec::new();
+        for square in (0..64).filter(|square| self.pieces[player] & (1 << square) != 0) {
+            let to = 1u64 << square;
+            let is_king = self.kings & to != 0;
+            let row_steps: &[isize] = if is_king { &[-1, 1] } else if man_row_step < 0 { &[-1] } else { &[1] };
+
+            for row_step in row_steps {
+                for column_step in [-1, 1].iter() {
+                    let row = square as isize / size + row_step;
+                    let column = square as isize % size + column_step;
+                    if !(0..size).contains(&row) || !(0..size).contains(&column) {
+                        continue;
+                    }
+                    let from = 1u64 << (row * size + column);
+                    if occupied & from != 0 {
+                        continue;
+                    }
+
+                    let mut previous = *self;
+                    previous.pieces[player] = (previous.pieces[player] & !to) | from;
+                    if is_king {
+                        previous.kings = (previous.kings & !to) | from;
+                    }
+                    previous.to_move = player;
+
+                    let bit_board = BitBoard::from_masks(previous.pieces, previous.kings);
+                    let player_id = Player { id: player as u32 + 1 };
+                    if bit_board.find_jump_moves(&player_id, DIRECTIONS[player]).is_empty() {
+                        previous_positions.push(previous);
+                    }
+                }
+            }
+        }
+        previous_positions
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+}
+
+// Rank of a combination of dark squares among the squares not `used`
+fn rank(squares: u32, used: u32) -> usize {
+    (0..SQUARES).filter(|square| squares & (1 << square) != 0)
+        .enumerate()
+        .map(|(i, square)| {
+            let free_below = square - (used & ((1 << square) - 1)).count_ones() as usize;
+            binomial(free_below, i + 1)
+        })
+        .sum()
+}
+
+// The combination of `count` dark squares not `used` with the given rank
+fn unrank(mut rank: usize, count: usize, used: u32) -> u32 {
+    let free_squares: Vec<usize> = (0..SQUARES).filter(|square| used & (1 << square) == 0).collect();
+    let mut squares = 0;
+    for i in (1..=count).rev() {
+        let mut free_below = i - 1;
+        while binomial(free_below + 1, i) <= rank {
+            free_below += 1;
+        }
+        rank -= binomial(free_below, i);
+        squares |= 1 << free_squares[free_below];
+    }
+    squares
+}
+
+// Dark square `index` holds square number `index + 1` in draughts notation
+fn dark_square_bit(index: usize) -> usize {
+    let row = index / 4;
+    let column = CHECKERBOARD_SIZE - 1 - 2 * (index % 4) - (1 - row % 2);
+    row * CHECKERBOARD_SIZE + column
+}
+
+// The bitboard of the dark squares of a mask
+fn board_mask(squares: u32) -> u64 {
+    (0..SQUARES).filter(|index| squares & (1 << index) != 0)
+        .fold(0, |mask, index| mask | 1 << dark_square_bit(index))
+}
+
+// The dark squares of a bitboard
+fn dark_mask(mask: u64) -> u32 {
+    (0..SQUARES).filter(|index| mask & (1 << dark_square_bit(*index)) != 0)
+        .fold(0, |squares, index| squares | 1 << index)
+}
+
+fn position_mask(position: BoardPosition) -> u64 {
+    1 << (position.row * CHECKERBOARD_SIZE + position.column)
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::sync::OnceLock;
+
+    use super::*;
+    use crate::ai::search::WIN_SCORE;
+
+    fn american() -> &'static Tablebase {
+        static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+        TABLEBASE.get_or_init(|| {
+            // every position with two pieces, and the ones with three kings: all of
+            // them would take long to solve without optimizations
+            let mut tablebase = Tablebase::generate(GameVariant::American, 2);
+            tablebase.max_pieces = 3;
+            for kings in [[2, 1], [1, 2]].iter() {
+                let material = Material { men: [0, 0], kings: *kings };
+                let values = tablebase.solve(material);
+                tablebase.slices.insert(material, values);
+            }
+            tablebase
+        })
+    }
+
+    fn game_from_fen(fen: &str, variant: GameVariant) -> Game {
+        Game::from_position(parse_fen(fen, variant).ok().unwrap(), "alice".into(), "bob".into(),
+                            TokenBalance { token_id: None, balance: 0 }, variant)
+    }
+
+    // The result of a position from the results of the positions its moves lead to
+    fn searched_value(tablebase: &Tablebase, position: &Position) -> u8 {
+        let next_values: Vec<u8> = position.moves().iter()
+            .map(|the_move| tablebase.value(&position.play(the_move)).unwrap())
+            .collect();
+        if next_values.is_empty() {
+            return 1;
+        }
+
+        let wins = next_values.iter().filter(|value| **value != 0 && !tablebase.wins(**value as usize - 1));
+        if let Some(value) = wins.min() {
+            return value + 1;
+        }
+        if next_values.contains(&0) {
+            return 0;
+        }
+        next_values.iter().max().unwrap() + 1
+    }
+
+    #[test]
+    fn squares_follow_draughts_notation() {
+        for index in 0..SQUARES {
+            let bit = dark_square_bit(index);
+            let number = AmericanRules.square_number(bit / CHECKERBOARD_SIZE, bit % CHECKERBOARD_SIZE);
+            assert_eq!(Some(index + 1), number);
+            assert!(DARK_SQUARES & (1 << bit) != 0);
+        }
+    }
+
+    #[test]
+    fn indexes_round_trip() {
+        for material in [Material { men: [1, 2], kings: [1, 0] }, Material { men: [4, 0], kings: [0, 1] }].iter() {
+            for index in (0..2 * material.placements()).step_by(97) {
+                let position = Position::from_index(*material, index);
+                assert!(position.is_valid(), "{:?}", position);
+                assert_eq!(*material, position.material());
+                assert_eq!(index, position.index());
+            }
+        }
+    }
+
+    #[test]
+    fn indexes_hold_legal_placements_only() {
+        // a man of each player: 28 squares each, less the 24 they share when both are on the same square
+        assert_eq!(28 * 28 - 24, Material { men: [1, 1], kings: [0, 0] }.placements());
+        assert_eq!(28 * 31, Material { men: [1, 0], kings: [0, 1] }.placements());
+        assert_eq!(32 * 31 / 2 * 30, Material { men: [0, 0], kings: [2, 1] }.placements());
+    }
+
+    #[test]
+    fn results_agree_with_the_moves() {
+        for variant in [GameVariant::American, GameVariant::Giveaway].iter() {
+            let tablebase = Tablebase::generate(*variant, 2);
+            for (material, values) in &tablebase.slices {
+                for (index, value) in values.iter().enumerate() {
+                    let position = Position::from_index(*material, index);
+                    assert_eq!(searched_value(&tablebase, &position), *value, "{:?}", position);
+                }
+            }
+        }
+
+        let tablebase = american();
+        let material = Material { men: [0, 0], kings: [2, 1] };
+        for index in (0..2 * material.placements()).step_by(7) {
+            let position = Position::from_index(material, index);
+            assert_eq!(searched_value(tablebase, &position), tablebase.slices[&material][index]);
+        }
+    }
+
+    #[test]
+    fn two_kings_beat_one() {
+        let mut game = game_from_fen("B:WK28:BK1,K2", GameVariant::American);
+        let probe = american().probe_game(&game).unwrap();
+        assert_eq!(TablebaseResult::Win, probe.result);
+
+        // perfect play from both sides ends the game in the plies announced
+        let mut plies = 0;
+        while let Some(probe) = american().probe_game(&game) {
+            let game_state = probe.best_move.unwrap().apply(&mut game).ok().unwrap();
+            plies += 1;
+            if game_state != GameState::InProgress {
+                assert_eq!(GameState::GameOver { winner_id: 0 }, game_state);
+                break;
+            }
+        }
+        assert_eq!(probe.plies, plies);
+
+        let game = game_from_fen("W:WK28:BK1,K2", GameVariant::American);
+        assert_eq!(TablebaseResult::Loss, american().probe_game(&game).unwrap().result);
+    }
+
+    #[test]
+    fn probes() {
+        let draw = game_from_fen("B:WK28:BK1", GameVariant::American);
+        let probe = american().probe_game(&draw).unwrap();
+        assert_eq!(TablebaseResult::Draw, probe.result);
+        assert!(probe.best_move.is_some());
+
+        // 15x24 takes the last piece
+        let capture = game_from_fen("B:W19:B15", GameVariant::American);
+        let probe = american().probe_game(&capture).unwrap();
+        assert_eq!(TablebaseResult::Win, probe.result);
+        assert_eq!(1, probe.plies);
+        let square = |number| AmericanRules.square_position(number).unwrap();
+        assert_eq!(Some(SearchMove { positions: vec![square(15), square(24)], is_capture: true }),
+                   probe.best_move);
+
+        let too_many_pieces = game_from_fen("B:W19,20:B1", GameVariant::American);
+        assert_eq!(None, american().probe_game(&too_many_pieces));
+        let other_variant = game_from_fen("B:WK28:BK1", GameVariant::Italian);
+        assert_eq!(None, american().probe_game(&other_variant));
+    }
+
+    #[test]
+    fn search_plays_the_tablebase_moves() {
+        let tablebase = Rc::new(Tablebase::generate(GameVariant::American, 2));
+        let mut search = Search::new(EvalWeights::default(), 10).with_tablebase(tablebase.clone());
+        let limits = SearchLimits { max_depth: 6, max_nodes: 10_000 };
+
+        let game = game_from_fen("W:WK28:B15", GameVariant::American);
+        let probe = tablebase.probe_game(&game).unwrap();
+        let result = search.best_move(&game, limits);
+        assert_eq!(probe.best_move, result.best_move);
+        assert_eq!(0, result.nodes);
+
+        // three pieces are searched until 15x24, the only move, leads to the tablebase: 28x19 follows
+        let game = game_from_fen("B:W19,K28:B15", GameVariant::American);
+        let result = search.best_move(&game, limits);
+        let after_capture = tablebase.probe_game(&game_from_fen("W:WK28:B24", GameVariant::American)).unwrap();
+        assert_eq!(TablebaseResult::Win, after_capture.result);
+        assert_eq!(after_capture.plies as i32 + 1 - WIN_SCORE, result.score);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let tablebase = Tablebase::generate(GameVariant::Giveaway, 2);
+        let mut bytes = Vec::new();
+        tablebase.write(&mut bytes).unwrap();
+
+        let read = Tablebase::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(GameVariant::Giveaway, read.variant());
+        assert_eq!(2, read.max_pieces());
+        assert!(read.slices == tablebase.slices);
+
+        assert_eq!(io::ErrorKind::UnexpectedEof, Tablebase::read(&mut &bytes[..20]).err().unwrap().kind());
+        bytes[0] = b'X';
+        assert_eq!(io::ErrorKind::InvalidData, Tablebase::read(&mut bytes.as_slice()).err().unwrap().kind());
+    }
+
+    // Checks a sample of the positions of each material with 4 pieces:
+    // cargo test --release four_pieces_agree_with_the_moves -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn four_pieces_agree_with_the_moves() {
+        for variant in [GameVariant::American, GameVariant::Giveaway].iter() {
+            let start = std::time::Instant::now();
+            let tablebase = Tablebase::generate(*variant, 4);
+            println!("{:?} solved in {:?}", variant, start.elapsed());
+
+            let mut checked = 0;
+            for (material, values) in tablebase.slices.iter().filter(|(material, _)| material.pieces() == 4) {
+                for index in (0..values.len()).step_by(101) {
+                    let position = Position::from_index(*material, index);
+                    assert_eq!(searched_value(&tablebase, &position), values[index], "{:?}", position);
+                    checked += 1;
+                }
+            }
+            println!("{} positions checked", checked);
+        }
+    }
+
+    // TABLEBASE_PIECES=4 TABLEBASE_FILE=american4.tb cargo test --release generate_tablebase -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn generate_tablebase() {
+        let pieces = std::env::var("TABLEBASE_PIECES").ok().and_then(|pieces| pieces.parse().ok()).unwrap_or(4);
+        let variant = match std::env::var("TABLEBASE_VARIANT").as_deref() {
+            Ok("Giveaway") => GameVariant::Giveaway,
+            _ => GameVariant::American,
+        };
+        let path = std::env::var("TABLEBASE_FILE").unwrap_or_else(|_| format!("{:?}{}.tb", variant, pieces).to_lowercase());
+
+        let start = std::time::Instant::now();
+        let tablebase = Tablebase::generate(variant, pieces);
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        tablebase.write(&mut file).unwrap();
+
+        let positions: usize = tablebase.slices.values().map(|values| values.len()).sum();
+        println!("{} positions of up to {} pieces written to {} in {:?}", positions, pieces, path, start.elapsed());
+    }
+}
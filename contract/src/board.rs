@@ -1,6 +1,5 @@
-use piece::ManPiece;
 use player::Player;
-use tile::{EmptyTile, OccupiedTile, Tile};
+use tile::{EmptyTile, Tile};
 
 use crate::*;
 use crate::tile::TileToSave;
@@ -15,6 +14,21 @@ impl BoardPosition {
     pub fn new(row: usize, column: usize) -> BoardPosition {
         BoardPosition { row, column }
     }
+
+    /// Returns the positions strictly between this position and `other`,
+    /// which must lie on the same row, column or diagonal.
+    pub fn positions_between(&self, other: &BoardPosition) -> Vec<BoardPosition> {
+        let row_step = (other.row as isize - self.row as isize).signum();
+        let col_step = (other.column as isize - self.column as isize).signum();
+        let distance = util::absolute_diff(self.row, other.row)
+            .max(util::absolute_diff(self.column, other.column));
+
+        (1..distance)
+            .map(|i| BoardPosition::new(
+                (self.row as isize + row_step * i as isize) as usize,
+                (self.column as isize + col_step * i as isize) as usize))
+            .collect()
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -26,35 +40,14 @@ pub struct BoardToSave {
 
 impl From<BoardToSave> for Board {
     fn from(board_to_save: BoardToSave) -> Self {
-        let mut board = Board {
-            number_rows: CHECKERBOARD_SIZE,
-            number_columns: CHECKERBOARD_SIZE,
-            tiles: Vec::with_capacity(CHECKERS_NUMBER_TILES),
-        };
+        let mut board = Board::new(board_to_save.number_rows, board_to_save.number_columns);
 
         for row in 0..board_to_save.number_rows {
             for column in 0..board_to_save.number_columns {
                 let idx = board_to_save.indices_to_index(row, column);
-                let tile: Box<dyn Tile> =
-                    if let Some(tile_to_save) = &board_to_save.tiles[idx] {
-                        if tile_to_save.piece_type == PieceType::Man {
-                            let piece = ManPiece::new(&Player {
-                                id: tile_to_save.player_id
-                            });
-                            Box::new(OccupiedTile::new(Box::new(piece)))
-                        } else if tile_to_save.piece_type == PieceType::King {
-                            let piece = KingPiece::new(&Player {
-                                id: tile_to_save.player_id
-                            });
-                            Box::new(OccupiedTile::new(Box::new(piece)))
-                        } else {
-                            Box::new(EmptyTile)
-                        }
-                    } else {
-                        Box::new(EmptyTile)
-                    };
-
-                board.tiles.push(tile);
+                if let Some(tile_to_save) = &board_to_save.tiles[idx] {
+                    board.put_piece(row, column, tile_to_save.player_id, tile_to_save.piece_type);
+                }
             }
         }
 
@@ -63,31 +56,74 @@ impl From<BoardToSave> for Board {
 }
 
 impl BoardToSave {
-    pub fn new_checkerboard(player1: &Player, player2: &Player) -> BoardToSave {
+    /// Creates a `size` x `size` board where each player has men on the
+    /// dark squares of the `player_rows` rows closest to them.
+    pub fn new_checkerboard(size: usize, player_rows: usize, player1: &Player, player2: &Player) -> BoardToSave {
         if player1.id == player2.id {
             panic!("Player 1 and Player 2 have the same ID: {}", player1.id)
         }
 
         let mut board = BoardToSave {
-            number_rows: CHECKERBOARD_SIZE,
-            number_columns: CHECKERBOARD_SIZE,
-            tiles: Vec::with_capacity(CHECKERS_NUMBER_TILES),
+            number_rows: size,
+            number_columns: size,
+            tiles: Vec::with_capacity(size * size),
         };
 
-        BoardToSave::fill_even_row(&mut board, player1);
-        BoardToSave::fill_odd_row(&mut board, player1);
-        BoardToSave::fill_even_row(&mut board, player1);
+        for row in 0..size {
+            let player = if row < player_rows {
+                Some(player1)
+            } else if row >= size - player_rows {
+                Some(player2)
+            } else {
+                None
+            };
 
-        BoardToSave::fill_empty_row(&mut board);
-        BoardToSave::fill_empty_row(&mut board);
+            match player {
+                Some(player) if row % 2 == 0 => BoardToSave::fill_even_row(&mut board, player),
+                Some(player) => BoardToSave::fill_odd_row(&mut board, player),
+                None => BoardToSave::fill_empty_row(&mut board),
+            }
+        }
 
-        BoardToSave::fill_odd_row(&mut board, player2);
-        BoardToSave::fill_even_row(&mut board, player2);
-        BoardToSave::fill_odd_row(&mut board, player2);
+        board
+    }
+
+    /// Creates a `size` x `size` board where each player has men on every
+    /// square of the `player_rows` rows in front of their back row, which
+    /// is left empty.
+    pub fn new_orthogonal_board(size: usize, player_rows: usize, player1: &Player, player2: &Player) -> BoardToSave {
+        if player1.id == player2.id {
+            panic!("Player 1 and Player 2 have the same ID: {}", player1.id)
+        }
+
+        let mut board = BoardToSave {
+            number_rows: size,
+            number_columns: size,
+            tiles: Vec::with_capacity(size * size),
+        };
+
+        for row in 0..size {
+            if row >= 1 && row <= player_rows {
+                BoardToSave::fill_full_row(&mut board, player1);
+            } else if row >= size - 1 - player_rows && row < size - 1 {
+                BoardToSave::fill_full_row(&mut board, player2);
+            } else {
+                BoardToSave::fill_empty_row(&mut board);
+            }
+        }
 
         board
     }
 
+    fn fill_full_row(board: &mut BoardToSave, player: &Player) {
+        for _ in 0..board.number_columns {
+            board.tiles.push(Some(TileToSave {
+                player_id: player.id,
+                piece_type: PieceType::Man,
+            }));
+        }
+    }
+
     fn fill_even_row(board: &mut BoardToSave, player: &Player) {
         for t in 0..board.number_columns {
             let tile: Option<TileToSave> = if t % 2 == 0 {
@@ -127,27 +163,155 @@ impl BoardToSave {
     }
 }
 
+// Bits used by each playable square of a packed board
+const PACKED_SQUARE_BITS: usize = 3;
+// Value of a king's square: its player ID plus this flag
+const PACKED_KING_FLAG: u8 = 4;
+// Playable squares of an 8x8 board played on the dark squares only
+const DARK_SQUARES: usize = 32;
+
+/// The pieces of a board packed into a fixed-size array, 3 bits for each
+/// playable square in row by row order: 0 for an empty square, the player
+/// ID for a man and the player ID plus 4 for a king. The playable squares
+/// and the board size come from the rules, which pick the arm that holds
+/// them: the 32 dark squares of an 8x8 board fit in 12 bytes, the 50 of a
+/// 10x10 board and the 64 of an orthogonal 8x8 board in 24 bytes.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PackedBoard {
+    DarkSquares([u8; 12]),
+    AllSquares([u8; 24]),
+}
+
+impl PackedBoard {
+    /// # Panics
+    ///
+    /// Panics if a piece stands on a square the rules don't play on.
+    pub fn pack(board: &Board, rules: &dyn RuleSet) -> PackedBoard {
+        let size = rules.board_size();
+        let playable_squares = (0..size * size)
+            .filter(|square| rules.is_playable_square(square / size, square % size))
+            .count();
+        let mut packed_board = if playable_squares <= DARK_SQUARES {
+            PackedBoard::DarkSquares([0; 12])
+        } else {
+            PackedBoard::AllSquares([0; 24])
+        };
+
+        let mut square_index = 0;
+        for row in 0..board.number_rows {
+            for column in 0..board.number_columns {
+                let piece = board.get_tile(row, column).get_piece();
+                if !rules.is_playable_square(row, column) {
+                    assert!(piece.is_none(), "Piece on an unplayable square: {} {}", row, column);
+                    continue;
+                }
+
+                let value = match piece {
+                    None => 0,
+                    Some(piece) => match piece.get_type() {
+                        PieceType::Man => piece.get_player_id() as u8,
+                        PieceType::King => piece.get_player_id() as u8 | PACKED_KING_FLAG,
+                    }
+                };
+                packed_board.set_square(square_index, value);
+                square_index += 1;
+            }
+        }
+
+        packed_board
+    }
+
+    pub fn unpack(&self, rules: &dyn RuleSet) -> Board {
+        let size = rules.board_size();
+        let mut board = Board::new(size, size);
+
+        let mut square_index = 0;
+        for row in 0..size {
+            for column in 0..size {
+                if !rules.is_playable_square(row, column) {
+                    continue;
+                }
+
+                let value = self.get_square(square_index);
+                square_index += 1;
+                if value != 0 {
+                    let piece_type = if value & PACKED_KING_FLAG == 0 { PieceType::Man } else { PieceType::King };
+                    board.put_piece(row, column, (value & !PACKED_KING_FLAG) as u32, piece_type);
+                }
+            }
+        }
+
+        board
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            PackedBoard::DarkSquares(bytes) => bytes,
+            PackedBoard::AllSquares(bytes) => bytes,
+        }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            PackedBoard::DarkSquares(bytes) => bytes,
+            PackedBoard::AllSquares(bytes) => bytes,
+        }
+    }
+
+    fn get_square(&self, square_index: usize) -> u8 {
+        let bytes = self.bytes();
+        let mut value = 0;
+        for bit in 0..PACKED_SQUARE_BITS {
+            let position = square_index * PACKED_SQUARE_BITS + bit;
+            value |= ((bytes[position / 8] >> (position % 8)) & 1) << bit;
+        }
+        value
+    }
+
+    fn set_square(&mut self, square_index: usize, value: u8) {
+        let bytes = self.bytes_mut();
+        for bit in 0..PACKED_SQUARE_BITS {
+            let position = square_index * PACKED_SQUARE_BITS + bit;
+            bytes[position / 8] &= !(1 << (position % 8));
+            bytes[position / 8] |= ((value >> bit) & 1) << (position % 8);
+        }
+    }
+}
+
+// The tile returned for the squares without a piece
+static EMPTY_TILE: EmptyTile = EmptyTile;
+
+// The tiles returned for the squares with a piece, by player and then man or king
+static PIECE_TILES: [[TileToSave; 2]; 2] = [
+    [TileToSave { player_id: 1, piece_type: PieceType::Man }, TileToSave { player_id: 1, piece_type: PieceType::King }],
+    [TileToSave { player_id: 2, piece_type: PieceType::Man }, TileToSave { player_id: 2, piece_type: PieceType::King }],
+];
+
+/// A board stored as bitmasks: bit `row * number_columns + column` of a mask
+/// is set when the square holds such a piece, so that boards of up to 128
+/// squares are copied, compared and searched without a tile per square.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Board {
     number_rows: usize,
     number_columns: usize,
-    tiles: Vec<Box<dyn Tile>>,
+    /// Squares holding the pieces of player 1 and player 2
+    pieces: [u128; 2],
+    /// Squares holding a king of either player
+    kings: u128,
 }
 
 impl From<Board> for BoardToSave {
     fn from(board: Board) -> Self {
         let mut board_to_save = BoardToSave {
-            number_rows: CHECKERBOARD_SIZE,
-            number_columns: CHECKERBOARD_SIZE,
-            tiles: Vec::with_capacity(CHECKERS_NUMBER_TILES),
+            number_rows: board.number_rows,
+            number_columns: board.number_columns,
+            tiles: Vec::with_capacity(board.number_rows * board.number_columns),
         };
 
         for row in 0..board.number_rows {
             for column in 0..board.number_columns {
-                let idx = board.indices_to_index(row, column);
-
-                let tile = &board.tiles[idx];
                 let tile_to_save: Option<TileToSave> =
-                    match tile.get_piece() {
+                    match board.get_tile(row, column).get_piece() {
                         None => None,
                         Some(piece) =>
                             Some(TileToSave {
@@ -167,24 +331,14 @@ impl From<Board> for BoardToSave {
 impl From<Board> for BoardOutput {
     fn from(board: Board) -> Self {
 
-        let mut board_output = [
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE),
-            Vec::with_capacity(CHECKERBOARD_SIZE)
-            ];
+        let mut board_output: BoardOutput = (0..board.number_rows)
+            .map(|_| Vec::with_capacity(board.number_columns))
+            .collect();
 
         for row in 0..board.number_rows {
             for column in 0..board.number_columns {
-                let idx = board.indices_to_index(row, column);
-
-                let tile = &board.tiles[idx];
                 let tile_to_save: i8 =
-                    match tile.get_piece() {
+                    match board.get_tile(row, column).get_piece() {
                         None => 0,
                         Some(piece) =>
                             match piece.get_type() {
@@ -203,45 +357,23 @@ impl From<Board> for BoardOutput {
 }
 
 impl Board {
-    #[cfg(test)]
+    /// Creates a board without pieces
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board has more than 128 squares.
     pub fn new(number_rows: usize, number_columns: usize) -> Board {
-        let number_tiles = number_rows * number_columns;
-        let mut board = Board {
-            number_rows: number_rows,
-            number_columns: number_columns,
-            tiles: Vec::with_capacity(number_tiles),
-        };
-
-        for _ in 0..number_tiles {
-            board.tiles.push(Box::new(EmptyTile));
+        assert!(number_rows * number_columns <= 128, "Boards hold at most 128 squares");
+        Board {
+            number_rows,
+            number_columns,
+            pieces: [0; 2],
+            kings: 0,
         }
-
-        board
     }
 
-    pub fn new_checkerboard(player1: &Player, player2: &Player) -> Board {
-        if player1.id == player2.id {
-            panic!("Player 1 and Player 2 have the same ID: {}", player1.id)
-        }
-
-        let mut board = Board {
-            number_rows: CHECKERBOARD_SIZE,
-            number_columns: CHECKERBOARD_SIZE,
-            tiles: Vec::with_capacity(CHECKERS_NUMBER_TILES),
-        };
-
-        Board::fill_even_row(&mut board, player1);
-        Board::fill_odd_row(&mut board, player1);
-        Board::fill_even_row(&mut board, player1);
-
-        Board::fill_empty_row(&mut board);
-        Board::fill_empty_row(&mut board);
-
-        Board::fill_odd_row(&mut board, player2);
-        Board::fill_even_row(&mut board, player2);
-        Board::fill_odd_row(&mut board, player2);
-
-        board
+    pub fn new_checkerboard(size: usize, player_rows: usize, player1: &Player, player2: &Player) -> Board {
+        BoardToSave::new_checkerboard(size, player_rows, player1, player2).into()
     }
 
     pub fn number_rows(&self) -> usize {
@@ -252,26 +384,80 @@ impl Board {
         self.number_columns
     }
 
-    fn indices_to_index(&self, row: usize, column: usize) -> usize {
-        self.number_columns * row + column
+    /// The squares holding the pieces of player 1 and player 2, and the
+    /// squares holding a king of either player
+    pub(crate) fn masks(&self) -> ([u128; 2], u128) {
+        (self.pieces, self.kings)
+    }
+
+    fn square_mask(&self, row: usize, column: usize) -> u128 {
+        assert!(row < self.number_rows && column < self.number_columns,
+                "Square outside the board: {} {}", row, column);
+        1 << (self.number_columns * row + column)
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the pieces on the board.
+    /// Equal positions always produce equal keys.
+    pub fn position_key(&self) -> u64 {
+        let mut key: u64 = 0xcbf2_9ce4_8422_2325;
+        for mask in [self.pieces[0], self.pieces[1], self.kings].iter() {
+            for word in [*mask as u64, (*mask >> 64) as u64].iter() {
+                key ^= *word;
+                key = key.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        key
     }
 
     pub fn get_tile(&self, row: usize, column: usize) -> &dyn Tile {
-        let idx = self.indices_to_index(row, column);
-        &*self.tiles[idx]
+        let square = self.square_mask(row, column);
+        let king = (self.kings & square != 0) as usize;
+        if self.pieces[0] & square != 0 {
+            &PIECE_TILES[0][king]
+        } else if self.pieces[1] & square != 0 {
+            &PIECE_TILES[1][king]
+        } else {
+            &EMPTY_TILE
+        }
     }
 
+    /// # Panics
+    ///
+    /// Panics if the piece of the tile belongs to another player than 1 or 2.
     pub fn set_tile(
         &mut self,
         row: usize,
         column: usize,
         tile: Box<dyn Tile>) {
-        let idx = self.indices_to_index(row, column);
-        self.tiles[idx] = tile;
+        match tile.get_piece() {
+            Some(piece) => self.put_piece(row, column, piece.get_player_id(), piece.get_type()),
+            None => self.clear_tile(row, column),
+        }
+    }
+
+    fn put_piece(&mut self, row: usize, column: usize, player_id: u32, piece_type: PieceType) {
+        assert!(player_id == 1 || player_id == 2, "Boards hold the pieces of players 1 and 2: {}", player_id);
+
+        self.clear_tile(row, column);
+        let square = self.square_mask(row, column);
+        self.pieces[player_id as usize - 1] |= square;
+        if piece_type == PieceType::King {
+            self.kings |= square;
+        }
     }
 
     pub fn clear_tile(&mut self, row: usize, column: usize) {
-        self.set_tile(row, column, Box::new(EmptyTile));
+        let square = !self.square_mask(row, column);
+        self.pieces[0] &= square;
+        self.pieces[1] &= square;
+        self.kings &= square;
+    }
+
+    /// Turns the piece on a square into a king
+    pub fn crown(&mut self, row: usize, column: usize) {
+        let square = self.square_mask(row, column);
+        assert!((self.pieces[0] | self.pieces[1]) & square != 0, "No piece to crown: {} {}", row, column);
+        self.kings |= square;
     }
 
     pub fn swap_tiles(
@@ -280,38 +466,65 @@ impl Board {
         column1: usize,
         row2: usize,
         column2: usize) {
-        let idx1 = self.indices_to_index(row1, column1);
-        let idx2 = self.indices_to_index(row2, column2);
-        self.tiles.swap(idx1, idx2);
+        let squares = (self.square_mask(row1, column1), self.square_mask(row2, column2));
+        swap_bits(&mut self.pieces[0], squares);
+        swap_bits(&mut self.pieces[1], squares);
+        swap_bits(&mut self.kings, squares);
     }
+}
 
-    fn fill_even_row(board: &mut Board, player: &Player) {
-        for t in 0..board.number_columns {
-            let tile: Box<dyn Tile> = if t % 2 == 0 {
-                let piece = ManPiece::new(player);
-                Box::new(OccupiedTile::new(Box::new(piece)))
-            } else {
-                Box::new(EmptyTile)
-            };
-            board.tiles.push(tile);
-        }
+// Exchanges the bits of two squares of a mask
+fn swap_bits(mask: &mut u128, (square1, square2): (u128, u128)) {
+    if (*mask & square1 != 0) != (*mask & square2 != 0) {
+        *mask ^= square1 | square2;
     }
+}
 
-    fn fill_odd_row(board: &mut Board, player: &Player) {
-        for t in 0..board.number_columns {
-            let tile: Box<dyn Tile> = if t % 2 == 1 {
-                let piece = ManPiece::new(player);
-                Box::new(OccupiedTile::new(Box::new(piece)))
-            } else {
-                Box::new(EmptyTile)
-            };
-            board.tiles.push(tile);
+#[cfg(test)]
+mod test {
+    use near_sdk::borsh::BorshSerialize;
+
+    use super::*;
+
+    #[test]
+    fn packed_board_round_trip() {
+        let (player1, player2) = (Player { id: 1 }, Player { id: 2 });
+        for variant in [GameVariant::American, GameVariant::International, GameVariant::Turkish].iter() {
+            let rules = variant.rules();
+            let mut board: Board = rules.new_board(&player1, &player2).into();
+            // crown the last piece of player 2
+            let size = rules.board_size();
+            let last_square = (0..size * size).rev()
+                .find(|square| board.get_tile(square / size, square % size).get_piece().is_some())
+                .unwrap();
+            board.crown(last_square / size, last_square % size);
+
+            assert_eq!(board, PackedBoard::pack(&board, rules).unpack(rules));
         }
     }
 
-    fn fill_empty_row(board: &mut Board) {
-        for _ in 0..board.number_columns {
-            board.tiles.push(Box::new(EmptyTile));
+    #[test]
+    fn packed_board_is_smaller() {
+        let rules = GameVariant::American.rules();
+        let board = rules.new_board(&Player { id: 1 }, &Player { id: 2 });
+        let board_size = board.try_to_vec().unwrap().len();
+        let packed_board = PackedBoard::pack(&board.into(), rules);
+        let packed_board_size = packed_board.try_to_vec().unwrap().len();
+
+        // 12 bytes and the tag of the arm
+        assert!(matches!(packed_board, PackedBoard::DarkSquares(_)));
+        assert_eq!(13, packed_board_size);
+        assert!(packed_board_size * 8 < board_size);
+    }
+
+    #[test]
+    fn packed_board_arm_follows_the_rules() {
+        let (player1, player2) = (Player { id: 1 }, Player { id: 2 });
+        for variant in [GameVariant::International, GameVariant::Turkish].iter() {
+            let rules = variant.rules();
+            let board: Board = rules.new_board(&player1, &player2).into();
+
+            assert!(matches!(PackedBoard::pack(&board, rules), PackedBoard::AllSquares(_)));
         }
     }
 }
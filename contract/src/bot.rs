@@ -0,0 +1,213 @@
+use near_sdk::json_types::U128;
+use near_sdk::{Gas, Promise};
+
+use crate::*;
+
+// Positions the bot searches for a move, few enough to stay within BOT_MIN_GAS
+// on the 10x10 board, as the tests of sim/ measure
+const BOT_MAX_NODES: u64 = 500;
+const BOT_MAX_DEPTH: u32 = 8;
+// Gas a move against the bot must attach, so that the bot isn't weakened by a
+// search cut short by the gas
+const BOT_MIN_GAS: Gas = 150_000_000_000_000;
+// Gas left to save the game once the search of the bot stops
+const BOT_GAS_RESERVE: Gas = 40_000_000_000_000;
+// The bot's transposition table has 2^10 entries
+const BOT_TABLE_BITS: u32 = 10;
+// Views have more gas to spend than the move of the bot
+const SUGGEST_MAX_NODES: u64 = 2_000;
+const SUGGEST_MAX_DEPTH: u32 = 12;
+
+/// Finds and plays the move of the bot. The search is deterministic: the
+/// same position always gets the same answer.
+pub(crate) fn play_bot_move(game: &mut Game) -> GameState {
+    let limits = SearchLimits { max_depth: BOT_MAX_DEPTH, max_nodes: BOT_MAX_NODES };
+    let result = Search::new(EvalWeights::default(), BOT_TABLE_BITS)
+        .with_stop(is_bot_out_of_gas)
+        .best_move(game, limits);
+    let the_move = result.best_move.expect("The bot has no move in a game in progress");
+    the_move.apply(game).expect("The bot plays legal moves")
+}
+
+// Whether the search must stop to leave enough gas to finish the call
+fn is_bot_out_of_gas() -> bool {
+    env::used_gas() + BOT_GAS_RESERVE > env::prepaid_gas()
+}
+
+/// Searches the move the engine would play in a game in progress, for hints
+pub(crate) fn suggest_move(game: &Game, depth: u32, weights: EvalWeights) -> MoveSuggestion {
+    let limits = SearchLimits { max_depth: depth.min(SUGGEST_MAX_DEPTH), max_nodes: SUGGEST_MAX_NODES };
+    let result = Search::new(weights, BOT_TABLE_BITS).best_move(game, limits);
+    let the_move = result.best_move.expect("The player to move has no move");
+
+    let (simple_move, jump_move) = if the_move.is_capture {
+        (None, Some(JumpMove::from_sequence(&the_move.positions)))
+    } else {
+        let (from, to) = (the_move.positions[0], the_move.positions[1]);
+        (Some(SimpleMove::new(from.row, from.column, to.row, to.column)), None)
+    };
+
+    MoveSuggestion {
+        simple_move,
+        jump_move,
+        score: result.score,
+        depth: result.depth,
+        evaluation: evaluate_position(game, &weights),
+    }
+}
+
+impl Checkers {
+    /// Starts a game against the bot, which plays as the contract account.
+    /// The house pool matches the deposit of the player, up to the maximum stake set by the owner.
+    pub(crate) fn internal_start_bot_game(&mut self, account_id: &AccountId, deposit: Balance, config: GameConfig) -> GameId {
+        assert!(config.opponent_id.is_none(), "Games against the bot have no opponent to choose");
+        assert!(deposit <= self.max_bot_stake,
+                "The stake is over the maximum against the bot. Attached: {}, Maximum: {}", deposit, self.max_bot_stake);
+        assert!(self.house_pool >= deposit,
+                "The house pool can't match the deposit. Attached: {}, Pool: {}", deposit, self.house_pool);
+        self.house_pool -= deposit;
+
+        let bot_id = env::current_account_id();
+        let reward = TokenBalance {
+            token_id: Some("NEAR".into()),
+            balance: deposit * 2,
+        };
+
+        let player_moves_first = match config.first_move {
+            FirstMoveOptions::First => true,
+            FirstMoveOptions::Second => false,
+            FirstMoveOptions::Random => near_sdk::env::random_seed()[0] % 2 == 1,
+        };
+        let mut game_to_save = if player_moves_first {
+            GameToSave::new(account_id.clone(), bot_id.clone(), reward, config.variant)
+        } else {
+            GameToSave::new(bot_id.clone(), account_id.clone(), reward, config.variant)
+        };
+        // the bot has no one to accept a takeback
+        game_to_save.takebacks_allowed = false;
+        game_to_save.set_time_control(config.time_control);
+
+        let game_id = self.next_game_id;
+        self.next_game_id += 1;
+        self.available_games.insert(&game_id, &(account_id.clone(), bot_id.clone()));
+
+        let token_id = Some("NEAR".into());
+        self.internal_update_stats(&token_id, account_id, UpdateStatsAction::AddPlayedGame, None, None);
+        self.internal_update_stats(&token_id, &bot_id, UpdateStatsAction::AddPlayedGame, None, None);
+
+        if player_moves_first {
+            self.internal_save_game(&game_id, game_to_save);
+        } else {
+            let mut game: Game = game_to_save.into();
+            let game_state = self.internal_make_bot_move(&mut game);
+            self.internal_finish_turn(game_id, &mut game, game_state);
+            self.internal_save_played_game(&game_id, game);
+        }
+
+        log!("Game {} against the bot started", game_id);
+        game_id
+    }
+
+    /// Plays the move of the bot right after the move of the player
+    pub(crate) fn internal_make_bot_move(&mut self, game: &mut Game) -> GameState {
+        assert!(env::prepaid_gas() >= BOT_MIN_GAS,
+                "Attach at least {} Tgas to play against the bot", BOT_MIN_GAS / 1_000_000_000_000);
+        // the bot answers at once, its clock only gets the increment
+        game.charge_clock(true);
+
+        let game_state = play_bot_move(game);
+        game.turns += 1;
+        game_state
+    }
+}
+
+#[near_bindgen]
+impl Checkers {
+    /// Adds the attached deposit to the house pool
+    #[payable]
+    #[private]
+    pub fn fund_house_pool(&mut self) {
+        self.house_pool += util::attached_deposit();
+        log!("House pool: {}", self.house_pool);
+    }
+
+    /// Takes from the house pool the stakes not matched in games in progress
+    #[private]
+    pub fn withdraw_house_pool(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        assert!(amount.0 <= self.house_pool, "Not enough in the house pool: {}", self.house_pool);
+        self.house_pool -= amount.0;
+        Promise::new(receiver_id).transfer(amount.0)
+    }
+
+    pub fn get_house_pool(&self) -> U128 {
+        U128::from(self.house_pool)
+    }
+
+    /// Sets the largest stake the house pool matches in a game against the bot
+    #[private]
+    pub fn set_max_bot_stake(&mut self, amount: U128) {
+        self.max_bot_stake = amount.0;
+    }
+
+    pub fn get_max_bot_stake(&self) -> U128 {
+        U128::from(self.max_bot_stake)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{MockedBlockchain, testing_env};
+
+    use super::*;
+    use crate::ai::search::WIN_SCORE;
+
+    fn play_bot_game(variant: GameVariant, plies: usize) -> Vec<MoveRecord> {
+        // the search of the bot reads the gas of the call
+        testing_env!(VMContextBuilder::new().build());
+        let mut game = Game::new("alice".into(), "bob".into(),
+                                 TokenBalance { token_id: None, balance: 0 }, variant);
+        for _ in 0..plies {
+            if play_bot_move(&mut game) != GameState::InProgress {
+                break;
+            }
+        }
+        game.moves
+    }
+
+    #[test]
+    fn bot_moves_are_deterministic() {
+        let moves = play_bot_game(GameVariant::American, 30);
+        assert_eq!(30, moves.len());
+        assert_eq!(moves, play_bot_game(GameVariant::American, 30));
+    }
+
+    #[test]
+    fn suggestions() {
+        let square = |number| AmericanRules.square_position(number).unwrap();
+        let game = Game::from_position(parse_fen("B:W14:B10,11", GameVariant::American).ok().unwrap(),
+                                       "alice".into(), "bob".into(),
+                                       TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        let suggestion = suggest_move(&game, 3, EvalWeights::default());
+        assert_eq!(None, suggestion.simple_move);
+        assert_eq!(Some(JumpMove::from_sequence(&[square(10), square(17)])), suggestion.jump_move);
+        // taking the last piece wins at once, the search goes no deeper
+        assert_eq!(WIN_SCORE - 1, suggestion.score);
+        assert_eq!(1, suggestion.depth);
+        assert_eq!(100, suggestion.evaluation.material);
+
+        let game = Game::new("alice".into(), "bob".into(),
+                             TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        let suggestion = suggest_move(&game, 50, EvalWeights::default());
+        assert!(suggestion.simple_move.is_some());
+        assert!(suggestion.depth <= SUGGEST_MAX_DEPTH);
+        assert_eq!(0, suggestion.evaluation.total);
+    }
+
+    #[test]
+    fn bot_plays_every_variant() {
+        for variant in [GameVariant::International, GameVariant::Italian, GameVariant::Turkish, GameVariant::Giveaway] {
+            assert_eq!(6, play_bot_game(variant, 6).len());
+        }
+    }
+}
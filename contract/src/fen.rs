@@ -0,0 +1,188 @@
+use crate::*;
+use crate::pdn::first_player_is_black;
+
+/// A position read from a FEN string
+pub struct FenPosition {
+    pub board: BoardToSave,
+    pub current_player_index: usize,
+}
+
+/// Why a FEN string can't be read
+#[derive(Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// The string isn't made of the side to move and the pieces of both colors
+    InvalidFormat(String),
+    /// A piece is on a square that isn't on the board
+    InvalidSquare(String),
+    /// Two pieces are on the same square
+    DuplicateSquare(usize),
+    /// A man is on the row where it would have been crowned
+    ManOnPromotionRow(usize),
+}
+
+/// Reads a position in the FEN notation of PDN, such as
+/// `W:W21,22,K30:B1,2,K9`: the color to move, then the squares of the
+/// white and black pieces, kings prefixed by `K`. A range `B1-12` lists
+/// several squares at once.
+pub fn parse_fen(fen: &str, variant: GameVariant) -> Result<FenPosition, FenError> {
+    let rules = variant.rules();
+    let invalid_format = || FenError::InvalidFormat(fen.to_string());
+
+    let fen = fen.trim().trim_matches('"').trim_end_matches('.');
+    let mut sections = fen.split(':').map(|section| section.trim());
+    let to_move = color_index(sections.next().ok_or_else(invalid_format)?, variant)
+        .ok_or_else(invalid_format)?;
+
+    let size = rules.board_size();
+    let mut board = Board::new(size, size);
+    let players = [Player { id: 1 }, Player { id: 2 }];
+    let directions = [Direction::IncreasingRank, Direction::DecreasingRank];
+    let mut colors_seen = [false, false];
+
+    for section in sections {
+        let color = section.get(..1).and_then(|color| color_index(color, variant))
+            .ok_or_else(invalid_format)?;
+        if colors_seen[color] {
+            return Err(invalid_format());
+        }
+        colors_seen[color] = true;
+
+        for token in section[1..].split(',').map(|token| token.trim()).filter(|token| !token.is_empty()) {
+            let is_king = token.starts_with('K');
+            for number in read_squares(token.trim_start_matches('K'))
+                .ok_or_else(|| FenError::InvalidSquare(token.to_string()))? {
+                let position = rules.square_position(number)
+                    .ok_or_else(|| FenError::InvalidSquare(token.to_string()))?;
+                if board.get_tile(position.row, position.column).get_piece().is_some() {
+                    return Err(FenError::DuplicateSquare(number));
+                }
+
+                let piece: Box<dyn Piece> = if is_king {
+                    Box::new(KingPiece::new(&players[color]))
+                } else if rules.is_promotion_row(&board, directions[color], position.row) {
+                    return Err(FenError::ManOnPromotionRow(number));
+                } else {
+                    Box::new(ManPiece::new(&players[color]))
+                };
+                board.set_tile(position.row, position.column, Box::new(OccupiedTile::new(piece)));
+            }
+        }
+    }
+
+    if colors_seen != [true, true] {
+        return Err(invalid_format());
+    }
+
+    Ok(FenPosition { board: board.into(), current_player_index: to_move })
+}
+
+/// Writes the position of a game as a FEN string
+pub fn game_to_fen(game: &Game) -> String {
+    board_to_fen(&game.board, game.variant, game.current_player_index)
+}
+
+/// Writes a board as a FEN string, with the player at `current_player_index` to move
+pub fn board_to_fen(board: &Board, variant: GameVariant, current_player_index: usize) -> String {
+    let rules = variant.rules();
+    let mut squares: [Vec<String>; 2] = [Vec::new(), Vec::new()];
+
+    // square numbers don't follow the rows, sort them as PDN tools expect
+    let mut pieces: Vec<(usize, u32, PieceType)> = Vec::new();
+    for row in 0..board.number_rows() {
+        for column in 0..board.number_columns() {
+            if let (Some(number), Some(piece)) =
+                (rules.square_number(row, column), board.get_tile(row, column).get_piece()) {
+                pieces.push((number, piece.get_player_id(), piece.get_type()));
+            }
+        }
+    }
+    pieces.sort_by_key(|(number, _, _)| *number);
+
+    for (number, player_id, piece_type) in pieces {
+        let prefix = if piece_type == PieceType::King { "K" } else { "" };
+        squares[player_id as usize - 1].push(format!("{}{}", prefix, number));
+    }
+
+    let white_index = if first_player_is_black(variant) { 1 } else { 0 };
+    let color = |index: usize| if index == white_index { "W" } else { "B" };
+
+    format!("{}:W{}:B{}",
+            color(current_player_index),
+            squares[white_index].join(","),
+            squares[1 - white_index].join(","))
+}
+
+// The index of the player playing the color `W` or `B`
+fn color_index(color: &str, variant: GameVariant) -> Option<usize> {
+    let white_index = if first_player_is_black(variant) { 1 } else { 0 };
+    match color {
+        "W" => Some(white_index),
+        "B" => Some(1 - white_index),
+        _ => None,
+    }
+}
+
+// A square number, or a range of them: "9-12"
+fn read_squares(token: &str) -> Option<Vec<usize>> {
+    match token.split_once('-') {
+        Some((first, last)) => {
+            let (first, last) = (first.parse::<usize>().ok()?, last.parse::<usize>().ok()?);
+            if first > last {
+                return None;
+            }
+            Some((first..=last).collect())
+        }
+        None => token.parse::<usize>().ok().map(|number| vec![number]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn starting_position() {
+        let game = Game::new("alice".into(), "bob".into(),
+                             TokenBalance { token_id: None, balance: 0 }, GameVariant::American);
+        assert_eq!("B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+                   game_to_fen(&game));
+
+        let position = parse_fen("B:W21-32:B1-12", GameVariant::American).ok().unwrap();
+        assert_eq!(0, position.current_player_index);
+        assert_eq!(game.board.position_key(), Board::from(position.board).position_key());
+    }
+
+    #[test]
+    fn round_trip_with_kings() {
+        let fen = "W:W21,22,K30:BK1,2,K9";
+        let position = parse_fen(fen, GameVariant::American).ok().unwrap();
+        assert_eq!(1, position.current_player_index);
+
+        let board = Board::from(position.board);
+        let square = AmericanRules.square_position(1).unwrap();
+        let king = board.get_tile(square.row, square.column).get_piece().unwrap();
+        assert_eq!(1, king.get_player_id());
+        assert!(king.get_type() == PieceType::King);
+        assert_eq!(fen, board_to_fen(&board, GameVariant::American, 1));
+
+        // White moves first in international draughts, and is the first player
+        let position = parse_fen("W:W31,K50:B1", GameVariant::International).ok().unwrap();
+        assert_eq!(0, position.current_player_index);
+        assert_eq!("W:W31,K50:B1",
+                   board_to_fen(&Board::from(position.board), GameVariant::International, 0));
+    }
+
+    #[test]
+    fn invalid_positions() {
+        let parse = |fen: &str| parse_fen(fen, GameVariant::American).err();
+
+        assert_eq!(Some(FenError::InvalidFormat("X:W21:B1".to_string())), parse("X:W21:B1"));
+        assert_eq!(Some(FenError::InvalidFormat("W:W21".to_string())), parse("W:W21"));
+        assert_eq!(Some(FenError::InvalidFormat("W:W21:W1".to_string())), parse("W:W21:W1"));
+        assert_eq!(Some(FenError::InvalidSquare("33".to_string())), parse("W:W21,33:B1"));
+        assert_eq!(Some(FenError::InvalidSquare("Kx".to_string())), parse("W:W21,Kx:B1"));
+        assert_eq!(Some(FenError::DuplicateSquare(21)), parse("W:W21:B1,K21"));
+        assert_eq!(Some(FenError::ManOnPromotionRow(2)), parse("W:W2:B9"));
+        assert_eq!(Some(FenError::ManOnPromotionRow(30)), parse("W:W21:B30"));
+    }
+}
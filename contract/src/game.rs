@@ -1,9 +1,10 @@
 // Need the following import statement for compiling
 // the tests but not for compiling the application.
 use near_sdk::{PanicOnDefault, Timestamp};
+use near_sdk::serde::{Deserialize, Serialize};
 
 use crate::*;
-use crate::board::BoardToSave;
+use crate::board::{BoardToSave, PackedBoard};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum GameState {
@@ -12,23 +13,117 @@ pub enum GameState {
 
     /// The game has finished. The `usize` is the index of the winning player: current_player_index
     GameOver { winner_id: usize },
+
+    /// The game has finished without a winner: the same position was repeated
+    /// three times, or too many moves were made without a capture or a man move
+    Draw,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum MoveError {
     InvalidMove,
     ShouldHaveJumped,
+    /// The jumping piece stopped while it could still capture
+    IncompleteJump,
+    /// The capture is legal but the rules require another capture to be taken first
+    NotPriorityCapture,
+}
+
+/// A move made in a game, kept so that finished games can be reviewed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MoveRecord {
+    /// Index of the player who moved
+    pub player_index: u8,
+    /// Block timestamp of the move
+    pub timestamp: Timestamp,
+    /// Squares the piece went through as (row, column), from its initial square to its final one
+    pub positions: Vec<(u8, u8)>,
+    /// Squares of the captured pieces
+    pub captures: Vec<(u8, u8)>,
+    /// Whether the moving man was promoted to king
+    pub promoted: bool,
+}
+
+impl MoveRecord {
+    fn new(player_index: usize, timestamp: Timestamp, positions: &[BoardPosition],
+           captures: &[BoardPosition], promoted: bool) -> MoveRecord {
+        let to_square = |position: &BoardPosition| (position.row as u8, position.column as u8);
+
+        MoveRecord {
+            player_index: player_index as u8,
+            timestamp,
+            positions: positions.iter().map(to_square).collect(),
+            captures: captures.iter().map(to_square).collect(),
+            promoted,
+        }
+    }
+}
+
+/// The position before a move, kept so that the move can be taken back
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct TakebackPosition {
+    /// Index of the player who moved from this position
+    pub(crate) player_index: usize,
+    pub(crate) board: PackedBoard,
+    pub(crate) moves_without_progress: u32,
+    /// Length of the repetition history in this position. The history is cut
+    /// back to it when no capture or man move cleared it since, otherwise it
+    /// starts again from this position.
+    pub(crate) history_length: u32,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Clone)]
 pub struct PlayerInfo {
-    player: Player,
-    direction: Direction,
+    pub(crate) player: Player,
+    pub(crate) direction: Direction,
     pub(crate) account_id: AccountId,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct GameToSave {
+    pub(crate) player_1: PlayerInfo,
+    pub(crate) player_2: PlayerInfo,
+    pub(crate) reward: TokenBalance,
+    pub(crate) winner_index: Option<usize>,
+    pub(crate) is_draw: bool,
+    pub(crate) turns: u64,
+    pub(crate) last_turn_timestamp: Timestamp,
+    pub(crate) total_time_spent: Vec<Timestamp>,
+
+    pub(crate) variant: GameVariant,
+    pub(crate) board: PackedBoard,
+
+    pub(crate) current_player_index: usize,
+
+    // positions reached since the last capture or man move, used for draw detection
+    pub(crate) moves_without_progress: u32,
+    pub(crate) position_history: Vec<u64>,
+
+    // index of the player whose draw offer is pending
+    pub(crate) draw_offer_index: Option<usize>,
+
+    // number of moves recorded, the moves themselves are stored apart from the game
+    pub(crate) moves_count: u64,
+
+    // ranked games may be played without takebacks
+    pub(crate) takebacks_allowed: bool,
+    // index of the player whose takeback request is pending
+    pub(crate) takeback_request_index: Option<usize>,
+    // positions before the last move of each player, the latest last
+    pub(crate) takeback_positions: Vec<TakebackPosition>,
+
+    pub(crate) time_control: TimeControl,
+    // time left on the clock of each player as of the last turn, the vacation left in correspondence games
+    pub(crate) remaining_time: Vec<Timestamp>,
+    // vacation the player to move took on their move, pushing its deadline back
+    pub(crate) vacation_time: Timestamp,
+}
+
+/// Games saved before the migration to versioned games, in the layout the
+/// contract was first deployed with
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct GameToSaveV1 {
     pub(crate) player_1: PlayerInfo,
     pub(crate) player_2: PlayerInfo,
     pub(crate) reward: TokenBalance,
@@ -42,19 +137,80 @@ pub struct GameToSave {
     pub(crate) current_player_index: usize,
 }
 
+impl From<GameToSaveV1> for GameToSave {
+    fn from(game: GameToSaveV1) -> Self {
+        let variant = GameVariant::American;
+        let time_control = TimeControl::default();
+        let remaining_time = game.total_time_spent.iter()
+            .map(|spent| time_control.initial_time().saturating_sub(*spent))
+            .collect();
+
+        GameToSave {
+            player_1: game.player_1,
+            player_2: game.player_2,
+            reward: game.reward,
+            winner_index: game.winner_index,
+            is_draw: false,
+            turns: game.turns,
+            last_turn_timestamp: game.last_turn_timestamp,
+            total_time_spent: game.total_time_spent,
+            variant,
+            board: PackedBoard::pack(&game.board.into(), variant.rules()),
+            current_player_index: game.current_player_index,
+            moves_without_progress: 0,
+            position_history: Vec::new(),
+            draw_offer_index: None,
+            moves_count: 0,
+            takebacks_allowed: true,
+            takeback_request_index: None,
+            takeback_positions: Vec::new(),
+            time_control,
+            remaining_time,
+            vacation_time: 0,
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum VGameToSave {
+    Current(GameToSave),
+}
+
+impl From<VGameToSave> for GameToSave {
+    fn from(v_game: VGameToSave) -> Self {
+        match v_game {
+            VGameToSave::Current(game) => game,
+        }
+    }
+}
+
 impl From<GameToSave> for Game {
     fn from(game_to_save: GameToSave) -> Self {
-        let board: Board = game_to_save.board.into();
+        let board = game_to_save.board.unpack(game_to_save.variant.rules());
 
         let mut game = Game {
             players: [game_to_save.player_1, game_to_save.player_2],
             reward: game_to_save.reward,
             winner_index: game_to_save.winner_index,
+            is_draw: game_to_save.is_draw,
             turns: game_to_save.turns,
             last_turn_timestamp: game_to_save.last_turn_timestamp,
             total_time_spent: game_to_save.total_time_spent,
+            variant: game_to_save.variant,
             board,
             current_player_index: game_to_save.current_player_index,
+            moves_without_progress: game_to_save.moves_without_progress,
+            position_history: game_to_save.position_history,
+            draw_offer_index: game_to_save.draw_offer_index,
+            moves: Vec::new(),
+            moves_count: game_to_save.moves_count,
+            takebacks_allowed: game_to_save.takebacks_allowed,
+            takeback_request_index: game_to_save.takeback_request_index,
+            takeback_positions: game_to_save.takeback_positions,
+            time_control: game_to_save.time_control,
+            remaining_time: game_to_save.remaining_time,
+            vacation_time: game_to_save.vacation_time,
+            block_timestamp: env::block_timestamp(),
             available_simple_moves: Vec::new(),
             available_jump_moves: Vec::new(),
         };
@@ -66,16 +222,18 @@ impl From<GameToSave> for Game {
 }
 
 impl GameToSave {
-    pub fn new(account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance) -> GameToSave {
+    pub fn new(account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance,
+               variant: GameVariant) -> GameToSave {
         let (player1, player2) = Game::create_two_players();
 
-        let board: BoardToSave = BoardToSave::new_checkerboard(&player1, &player2);
+        let board: BoardToSave = variant.rules().new_board(&player1, &player2);
 
-        GameToSave::with_board_and_players(board, player1, player2, account_id_1, account_id_2, reward)
+        GameToSave::with_board_and_players(board, player1, player2, account_id_1, account_id_2, reward, variant)
     }
 
     fn with_board_and_players(board: BoardToSave, player1: Player, player2: Player,
-                              account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance)
+                              account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance,
+                              variant: GameVariant)
                               -> GameToSave {
         let player1_info = PlayerInfo {
             player: player1,
@@ -88,66 +246,175 @@ impl GameToSave {
             account_id: account_id_2,
         };
 
+        let board: Board = board.into();
+        let packed_board = PackedBoard::pack(&board, variant.rules());
+        let start_position_key = Game::position_key_of(&board, 0);
+
         GameToSave {
             player_1: player1_info,
             player_2: player2_info,
             reward,
             winner_index: None,
+            is_draw: false,
             turns: 0,
             last_turn_timestamp: env::block_timestamp(),
             total_time_spent: [0, 0].to_vec(),
-            board,
+            variant,
+            board: packed_board,
             current_player_index: 0,
+            moves_without_progress: 0,
+            position_history: vec![start_position_key],
+            draw_offer_index: None,
+            moves_count: 0,
+            takebacks_allowed: true,
+            takeback_request_index: None,
+            takeback_positions: Vec::new(),
+            time_control: TimeControl::default(),
+            remaining_time: vec![TimeControl::default().initial_time(); 2],
+            vacation_time: 0,
         }
     }
+
+    /// Sets the time control of a game that hasn't started yet
+    pub(crate) fn set_time_control(&mut self, time_control: TimeControl) {
+        self.time_control = time_control;
+        self.remaining_time = vec![time_control.initial_time(); 2];
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.winner_index.is_some() || self.is_draw
+    }
+
+    /// Returns the index of the player with the given account.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the account doesn't play this game.
+    pub fn player_index(&self, account_id: &AccountId) -> usize {
+        if *account_id == self.player_1.account_id {
+            0
+        } else if *account_id == self.player_2.account_id {
+            1
+        } else {
+            panic!("No access")
+        }
+    }
+
+    /// Offers the opponent of `player_index` a draw, until they accept or
+    /// decline it, or until the next move
+    pub(crate) fn offer_draw(&mut self, player_index: usize) {
+        assert!(self.draw_offer_index.is_none(), "Draw already offered");
+        self.draw_offer_index = Some(player_index);
+    }
+
+    /// Ends the game in a draw offered by the opponent of `player_index`
+    pub(crate) fn accept_draw(&mut self, player_index: usize) {
+        assert_eq!(self.draw_offer_index, Some(1 - player_index), "No draw offer from the opponent");
+        self.is_draw = true;
+        self.draw_offer_index = None;
+    }
+
+    pub(crate) fn decline_draw(&mut self, player_index: usize) {
+        assert_eq!(self.draw_offer_index, Some(1 - player_index), "No draw offer from the opponent");
+        self.draw_offer_index = None;
+    }
+
+    /// Whether `player_index` made a move that can be taken back
+    pub(crate) fn can_take_back(&self, player_index: usize) -> bool {
+        self.takeback_positions.iter().any(|position| position.player_index == player_index)
+    }
 }
 
+#[derive(Clone)]
 pub struct Game {
     pub(crate) players: [PlayerInfo; 2],
     pub(crate) reward: TokenBalance,
     pub(crate) winner_index: Option<usize>,
+    pub(crate) is_draw: bool,
     pub(crate) turns: u64,
     pub(crate) last_turn_timestamp: Timestamp,
     pub(crate) total_time_spent: Vec<Timestamp>,
+    pub(crate) variant: GameVariant,
     pub(crate) board: Board,
 
     pub(crate) current_player_index: usize,
 
+    pub(crate) moves_without_progress: u32,
+    pub(crate) position_history: Vec<u64>,
+
+    pub(crate) draw_offer_index: Option<usize>,
+
+    // the moves made since the game was loaded, out of `moves_count` moves in all
+    pub(crate) moves: Vec<MoveRecord>,
+    pub(crate) moves_count: u64,
+
+    pub(crate) takebacks_allowed: bool,
+    pub(crate) takeback_request_index: Option<usize>,
+    pub(crate) takeback_positions: Vec<TakebackPosition>,
+
+    pub(crate) time_control: TimeControl,
+    pub(crate) remaining_time: Vec<Timestamp>,
+    pub(crate) vacation_time: Timestamp,
+
+    // time of the block the game is played in, recorded with the moves made
+    pub(crate) block_timestamp: Timestamp,
+
     pub(crate) available_simple_moves: Vec<SimpleMove>,
     pub(crate) available_jump_moves: Vec<JumpMove>,
 }
 
 impl From<Game> for GameToSave {
     fn from(game: Game) -> Self {
-        let board: BoardToSave = game.board.into();
-
-        let already_spent = game.total_time_spent[1 - game.current_player_index];
-        let spent_for_this_turn = env::block_timestamp() - game.last_turn_timestamp;
-
-        let mut total_time_spent = game.total_time_spent;
-        total_time_spent[1 - game.current_player_index] = already_spent + spent_for_this_turn;
-
         GameToSave {
             player_1: game.players[0].clone(),
             player_2: game.players[1].clone(),
             reward: game.reward,
             winner_index: game.winner_index,
+            is_draw: game.is_draw,
             turns: game.turns,
-            last_turn_timestamp: env::block_timestamp(),
-            total_time_spent,
-            board,
+            last_turn_timestamp: game.last_turn_timestamp,
+            total_time_spent: game.total_time_spent,
+            variant: game.variant,
+            board: PackedBoard::pack(&game.board, game.variant.rules()),
             current_player_index: game.current_player_index,
+            moves_without_progress: game.moves_without_progress,
+            position_history: game.position_history,
+            draw_offer_index: game.draw_offer_index,
+            moves_count: game.moves_count,
+            takebacks_allowed: game.takebacks_allowed,
+            takeback_request_index: game.takeback_request_index,
+            takeback_positions: game.takeback_positions,
+            time_control: game.time_control,
+            remaining_time: game.remaining_time,
+            vacation_time: game.vacation_time,
         }
     }
 }
 
 impl Game {
-    pub fn new(account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance) -> Game {
+    pub fn new(account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance,
+               variant: GameVariant) -> Game {
+        let (player1, player2) = Game::create_two_players();
+
+        let board: Board = variant.rules().new_board(&player1, &player2).into();
+
+        Game::with_board_and_players(board, player1, player2, account_id_1, account_id_2, reward, variant)
+    }
+
+    /// Creates a game starting from a position, such as one read with `parse_fen`
+    pub fn from_position(position: FenPosition, account_id_1: AccountId, account_id_2: AccountId,
+                         reward: TokenBalance, variant: GameVariant) -> Game {
         let (player1, player2) = Game::create_two_players();
 
-        let board = Board::new_checkerboard(&player1, &player2);
+        let mut game = Game::with_board_and_players(position.board.into(), player1, player2,
+                                                    account_id_1, account_id_2, reward, variant);
+        if game.current_player_index != position.current_player_index {
+            game.current_player_index = position.current_player_index;
+            game.position_history = vec![game.position_key()];
+            game.find_available_moves();
+        }
 
-        Game::with_board_and_players(board, player1, player2, account_id_1, account_id_2, reward)
+        game
     }
 
     /// Creates a new Checkers game with an 8x8 board and the specified piece
@@ -164,7 +431,16 @@ impl Game {
     pub fn with_piece_positions(player1_positions: Vec<BoardPosition>,
                                 player2_positions: Vec<BoardPosition>)
                                 -> Game {
-        let checkerboard_size: usize = 8;
+        Game::with_variant_and_piece_positions(GameVariant::American, player1_positions, player2_positions)
+    }
+
+    /// Same as `with_piece_positions`, for a game of the given variant.
+    #[cfg(test)]
+    pub fn with_variant_and_piece_positions(variant: GameVariant,
+                                            player1_positions: Vec<BoardPosition>,
+                                            player2_positions: Vec<BoardPosition>)
+                                            -> Game {
+        let checkerboard_size: usize = variant.rules().board_size();
         let mut board = Board::new(checkerboard_size, checkerboard_size);
 
         let (player1, player2) = Game::create_two_players();
@@ -174,7 +450,8 @@ impl Game {
 
         Game::with_board_and_players(board, player1, player2, "alice".into(),
                                      "bob".into(),
-                                     TokenBalance{ token_id: Some("NEAR".into()), balance: 1 })
+                                     TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                                     variant)
     }
 
     // creates and returns two players with distinct IDs
@@ -198,7 +475,8 @@ impl Game {
     }
 
     fn with_board_and_players(board: Board, player1: Player, player2: Player,
-                              account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance)
+                              account_id_1: AccountId, account_id_2: AccountId, reward: TokenBalance,
+                              variant: GameVariant)
                               -> Game {
         let player1_info = PlayerInfo {
             player: player1,
@@ -215,15 +493,30 @@ impl Game {
             players: [player1_info, player2_info],
             reward,
             winner_index: None,
+            is_draw: false,
             turns: 0,
             last_turn_timestamp: 0,
             total_time_spent: [0, 0].to_vec(),
+            variant,
             board,
             current_player_index: 0,
+            moves_without_progress: 0,
+            position_history: Vec::new(),
+            draw_offer_index: None,
+            moves: Vec::new(),
+            moves_count: 0,
+            takebacks_allowed: true,
+            takeback_request_index: None,
+            takeback_positions: Vec::new(),
+            time_control: TimeControl::default(),
+            remaining_time: vec![TimeControl::default().initial_time(); 2],
+            vacation_time: 0,
+            block_timestamp: 0,
             available_simple_moves: Vec::new(),
             available_jump_moves: Vec::new(),
         };
 
+        game.position_history.push(game.position_key());
         game.find_available_moves();
 
         game
@@ -233,66 +526,25 @@ impl Game {
         &self.players[self.current_player_index]
     }
 
-    fn find_available_simple_moves(&self) -> Vec<SimpleMove> {
-        let mut moves = Vec::new();
+    pub fn rules(&self) -> &'static dyn RuleSet {
+        self.variant.rules()
+    }
 
+    fn find_available_simple_moves(&self) -> Vec<SimpleMove> {
         let curr_player_info = self.current_player_info();
-        let curr_player = &curr_player_info.player;
-        let curr_player_id = curr_player.id;
-        let curr_direction = curr_player_info.direction;
-        for r in 0..self.board.number_rows() {
-            for c in 0..self.board.number_columns() {
-                match self.board.get_tile(r, c).get_piece() {
-                    Some(piece) =>
-                        if piece.get_player_id() == curr_player_id {
-                            let piece_moves = match piece.get_type() {
-                                PieceType::Man =>
-                                    ai::find_simple_moves_for_man(
-                                        &self.board, curr_direction, r, c),
-                                PieceType::King =>
-                                    ai::find_simple_moves_for_king(
-                                        &self.board, r, c),
-                            };
-                            moves.extend(piece_moves);
-                        },
-                    None => {}
-                }
-            }
-        }
-
-        moves
+        self.rules().find_all_simple_moves(
+            &self.board, &curr_player_info.player, curr_player_info.direction)
     }
 
     fn find_available_jump_moves(&self) -> Vec<JumpMove> {
-        let mut moves = Vec::new();
+        self.rules().filter_jump_moves(&self.board, self.find_all_jump_moves())
+    }
 
+    // All the jumps of the current player, before the capture rules choose among them
+    fn find_all_jump_moves(&self) -> Vec<JumpMove> {
         let curr_player_info = self.current_player_info();
-        let curr_player = &curr_player_info.player;
-        let curr_player_id = curr_player.id;
-        let curr_direction = curr_player_info.direction;
-        for r in 0..self.board.number_rows() {
-            for c in 0..self.board.number_columns() {
-                match self.board.get_tile(r, c).get_piece() {
-                    Some(piece) =>
-                        if piece.get_player_id() == curr_player_id {
-                            let jump_move = match piece.get_type() {
-                                PieceType::Man =>
-                                    ai::find_jump_moves_for_man(
-                                        &self.board, curr_player, curr_direction, r, c),
-                                PieceType::King =>
-                                    ai::find_jump_moves_for_king(
-                                        &self.board, curr_player, r, c),
-                            };
-                            if !jump_move.jumps().is_empty() {
-                                moves.push(jump_move);
-                            }
-                        },
-                    None => {}
-                }
-            }
-        }
-
-        moves
+        self.rules().find_all_jump_moves(
+            &self.board, &curr_player_info.player, curr_player_info.direction)
     }
 
     fn find_available_moves(&mut self) {
@@ -313,35 +565,37 @@ impl Game {
         self.players[index].account_id.clone()
     }
 
+    pub fn is_finished(&self) -> bool {
+        self.winner_index.is_some() || self.is_draw
+    }
+
     fn check_for_coronation
     (&mut self, row: usize, col: usize) {
         let coronate = match self.board.get_tile(row, col).get_piece() {
             Some(piece) =>
                 match piece.get_type() {
-                    PieceType::Man =>
-                        match self.current_player_info().direction {
-                            Direction::IncreasingRank =>
-                                row + 1 == self.board.number_rows(),
-                            Direction::DecreasingRank => row == 0
-                        },
+                    PieceType::Man => self.rules().is_promotion_row(
+                        &self.board, self.current_player_info().direction, row),
                     PieceType::King => false
                 },
             None => unreachable!()
         };
 
         if coronate {
-            let king = KingPiece::new(&self.current_player_info().player);
-            let tile = OccupiedTile::new(Box::new(king));
-            self.board.set_tile(row, col, Box::new(tile))
+            self.coronate(row, col);
         }
     }
 
+    fn coronate(&mut self, row: usize, col: usize) {
+        self.board.crown(row, col)
+    }
+
     fn select_next_player(&mut self) {
         // this assumes a two player game
         self.current_player_index = 1 - self.current_player_index;
     }
 
-    fn is_game_over(&self) -> bool {
+    pub(crate) fn is_game_over(&self) -> bool {
         // This works if it is called after the available moves for the
         // next player are computed. If this player has no moves, it means
         // they have no pieces left, or all of their pieces are stuck.
@@ -350,32 +604,226 @@ impl Game {
             && self.available_jump_moves.is_empty()
     }
 
-    fn finish_move(&mut self, final_row: usize, final_col: usize)
-                   -> GameState {
-        self.check_for_coronation(final_row, final_col);
+    // identifies the position on the board together with the player to move
+    fn position_key(&self) -> u64 {
+        Game::position_key_of(&self.board, self.current_player_index)
+    }
 
-        self.select_next_player();
+    fn position_key_of(board: &Board, current_player_index: usize) -> u64 {
+        board.position_key() ^ (current_player_index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    // Records the position reached by the last move. A capture or a man move
+    // can never be undone, so positions before it can't be repeated anymore.
+    fn record_position(&mut self, progress: bool) {
+        if progress {
+            self.moves_without_progress = 0;
+            self.position_history.clear();
+        } else {
+            self.moves_without_progress += 1;
+        }
+
+        let key = self.position_key();
+        self.position_history.push(key);
+    }
+
+    /// Keeps the position before the move the current player is about to
+    /// make, so that it can be taken back. Only the last move of each player is kept.
+    pub(crate) fn save_takeback_position(&mut self) {
+        if !self.takebacks_allowed {
+            return;
+        }
+        if self.takeback_positions.len() == 2 {
+            self.takeback_positions.remove(0);
+        }
+        self.takeback_positions.push(TakebackPosition {
+            player_index: self.current_player_index,
+            board: PackedBoard::pack(&self.board, self.rules()),
+            moves_without_progress: self.moves_without_progress,
+            history_length: self.position_history.len() as u32,
+        });
+    }
+
+    /// Takes back the last move of `player_index`, and the reply of the
+    /// opponent if there was one: the position before it comes back, with
+    /// `player_index` to move. The time spent so far stays on the clocks,
+    /// less the increments of the moves taken back, and the clock of
+    /// `player_index` runs from now on.
+    pub(crate) fn take_back(&mut self, player_index: usize) {
+        let index = self.takeback_positions.iter()
+            .rposition(|position| position.player_index == player_index)
+            .expect("No move to take back");
+        let moves_taken_back = self.takeback_positions.len() - index;
+
+        self.charge_clock(false);
+        // the increments earned by the moves taken back are lost
+        for position in &self.takeback_positions[index..] {
+            let remaining = &mut self.remaining_time[position.player_index];
+            *remaining = remaining.saturating_sub(self.time_control.increment());
+        }
+        let position = self.takeback_positions.drain(index..).next().unwrap();
+
+        self.board = position.board.unpack(self.rules());
+        self.current_player_index = player_index;
+        // each move since the position either counts without progress or resets the count
+        if self.moves_without_progress == position.moves_without_progress + moves_taken_back as u32 {
+            self.position_history.truncate(position.history_length as usize);
+        } else {
+            self.position_history = vec![self.position_key()];
+        }
+        self.moves_without_progress = position.moves_without_progress;
+        self.turns -= moves_taken_back as u64;
+        self.moves.truncate(self.moves.len().saturating_sub(moves_taken_back));
+        self.moves_count -= moves_taken_back as u64;
+        self.draw_offer_index = None;
+        self.takeback_request_index = None;
         self.find_available_moves();
+    }
+
+    /// Copies the position to search it: the moves played, the takeback
+    /// positions and the accounts are left out, as the search copies the game
+    /// at every node.
+    pub(crate) fn search_copy(&self) -> Game {
+        let player_copy = |player: &PlayerInfo| PlayerInfo {
+            player: player.player.clone(),
+            direction: player.direction,
+            account_id: AccountId::new(),
+        };
+
+        Game {
+            players: [player_copy(&self.players[0]), player_copy(&self.players[1])],
+            reward: TokenBalance { token_id: None, balance: 0 },
+            winner_index: self.winner_index,
+            is_draw: self.is_draw,
+            turns: self.turns,
+            last_turn_timestamp: self.last_turn_timestamp,
+            total_time_spent: Vec::new(),
+            variant: self.variant,
+            board: self.board,
+            current_player_index: self.current_player_index,
+            moves_without_progress: self.moves_without_progress,
+            position_history: self.position_history.clone(),
+            draw_offer_index: None,
+            moves: Vec::new(),
+            moves_count: self.moves_count,
+            takebacks_allowed: false,
+            takeback_request_index: None,
+            takeback_positions: Vec::new(),
+            time_control: self.time_control,
+            remaining_time: Vec::new(),
+            vacation_time: 0,
+            block_timestamp: self.block_timestamp,
+            available_simple_moves: self.available_simple_moves.clone(),
+            available_jump_moves: self.available_jump_moves.clone(),
+        }
+    }
+
+    // time the player to move has been thinking on their move
+    fn time_on_move(&self) -> Timestamp {
+        self.block_timestamp.saturating_sub(self.last_turn_timestamp)
+    }
 
-        if self.is_game_over() {
-            //let winner_id = self.players[1 - self.current_player_index].player.id;
-            GameState::GameOver { winner_id: (1 - self.current_player_index) }
+    /// Time left on the clock of `player_index`, whose clock runs if it's their turn
+    pub(crate) fn remaining_time(&self, player_index: usize) -> Timestamp {
+        let remaining = self.remaining_time[player_index];
+        if player_index == self.current_player_index {
+            remaining.saturating_sub(self.time_control.charged_time(self.time_on_move()))
         } else {
-            GameState::InProgress
+            remaining
+        }
+    }
+
+    /// Time after which the player to move loses on time
+    pub(crate) fn move_deadline(&self) -> Timestamp {
+        self.last_turn_timestamp + self.vacation_time
+            + self.time_control.move_time_limit(self.remaining_time[self.current_player_index])
+    }
+
+    /// Whether the player to move ran out of time for their move
+    pub(crate) fn is_out_of_time(&self) -> bool {
+        self.time_control.is_out_of_time(self.remaining_time[self.current_player_index],
+                                         self.time_on_move().saturating_sub(self.vacation_time))
+    }
+
+    /// Spends `days` of the vacation banked by the player to move, pushing
+    /// the deadline of their move back by as many days
+    pub(crate) fn take_vacation(&mut self, days: u64) {
+        assert!(days > 0, "Take at least one vacation day");
+        let vacation_time = self.time_control.vacation_time(days);
+        let index = self.current_player_index;
+        assert!(vacation_time <= self.remaining_time[index],
+                "Not enough vacation days left. Asked: {}, Left: {}",
+                days, self.remaining_time[index] / self.time_control.vacation_time(1));
+
+        self.remaining_time[index] -= vacation_time;
+        self.vacation_time += vacation_time;
+    }
+
+    /// Stops the clock of the player to move, at the end of their turn.
+    /// A move made in time earns them the increment of the time control.
+    pub(crate) fn charge_clock(&mut self, moved: bool) {
+        let elapsed = self.time_on_move();
+        let index = self.current_player_index;
+        self.total_time_spent[index] += elapsed;
+        self.remaining_time[index] = self.remaining_time(index);
+        if moved {
+            self.remaining_time[index] += self.time_control.increment();
         }
+        self.last_turn_timestamp = self.block_timestamp;
+        self.vacation_time = 0;
+    }
+
+    pub(crate) fn is_drawn_position(&self) -> bool {
+        let key = self.position_key();
+        let repetitions = self.position_history.iter()
+            .filter(|position| **position == key)
+            .count();
+
+        repetitions >= DRAW_REPETITIONS
+            || self.moves_without_progress >= self.rules().draw_moves_without_progress()
+    }
+
+    // `the_move` holds the squares the piece went through, `captures` the squares of the captured pieces
+    fn finish_move(&mut self, the_move: &[BoardPosition], captures: &[BoardPosition], man_moved: bool)
+                   -> GameState {
+        let final_position = the_move.last().unwrap();
+        self.check_for_coronation(final_position.row, final_position.column);
+
+        let promoted = man_moved && self.board.get_tile(final_position.row, final_position.column)
+            .get_piece()
+            .map(|piece| piece.get_type() == PieceType::King)
+            .unwrap_or(false);
+        self.moves.push(MoveRecord::new(
+            self.current_player_index, self.block_timestamp, the_move, captures, promoted));
+        self.moves_count += 1;
+
+        let progress = man_moved || !captures.is_empty();
+        self.select_next_player();
+        self.find_available_moves();
+        self.record_position(progress);
+
+        self.rules().game_state(self)
     }
 
     pub fn apply_simple_move(&mut self, the_move: SimpleMove) -> Result<GameState, MoveError> {
-        if self.available_jump_moves.is_empty() {
+        if self.available_jump_moves.is_empty() || !self.rules().is_capture_mandatory() {
             if self.available_simple_moves.contains(&the_move) {
+                let man_moved = self.board
+                    .get_tile(the_move.from_row(), the_move.from_column())
+                    .get_piece()
+                    .map(|piece| piece.get_type() == PieceType::Man)
+                    .unwrap_or(false);
+
                 self.board.swap_tiles(
                     the_move.from_row(),
                     the_move.from_column(),
                     the_move.to_row(),
                     the_move.to_column());
 
-                let game_state = self.finish_move(
-                    the_move.to_row(), the_move.to_column());
+                let positions = [
+                    BoardPosition::new(the_move.from_row(), the_move.from_column()),
+                    BoardPosition::new(the_move.to_row(), the_move.to_column())];
+                let game_state = self.finish_move(&positions, &[], man_moved);
                 Ok(game_state)
             } else {
                 Err(MoveError::InvalidMove)
@@ -387,10 +835,27 @@ impl Game {
 
     pub fn apply_jump_move(&mut self, the_move: Vec<BoardPosition>) -> Result<GameState, MoveError> {
         let jump_valid = self.available_jump_moves.iter()
-            .any(|jump_tree| jump_tree.contains_jump_sequence(&the_move));
+            .any(|jump_tree| jump_tree.contains_complete_jump_sequence(&the_move));
         if jump_valid {
             let start_position = the_move.first().unwrap();
             let final_position = the_move.last().unwrap();
+            let man_moved = self.board.get_tile(start_position.row, start_position.column)
+                .get_piece()
+                .map(|piece| piece.get_type() == PieceType::Man)
+                .unwrap_or(false);
+
+            // remove all jumped pieces, the jumping piece may pass its initial position
+            let mut captures = Vec::new();
+            let iter = the_move[0..].iter().zip(the_move[1..].iter());
+            for (jump_from_pos, jump_to_pos) in iter {
+                for jumped_pos in jump_from_pos.positions_between(jump_to_pos) {
+                    let occupied = self.board.get_tile(jumped_pos.row, jumped_pos.column).get_piece().is_some();
+                    if jumped_pos != *start_position && occupied {
+                        self.board.clear_tile(jumped_pos.row, jumped_pos.column);
+                        captures.push(jumped_pos);
+                    }
+                }
+            }
 
             // move the jumping piece
             self.board.swap_tiles(
@@ -399,19 +864,31 @@ impl Game {
                 final_position.row,
                 final_position.column);
 
-            // remove all jumped pieces
-            let iter = the_move[0..].iter().zip(the_move[1..].iter());
-            for (jump_from_pos, jump_to_pos) in iter {
-                let jumped_row = (jump_from_pos.row + jump_to_pos.row) / 2;
-                let jumped_col = (jump_from_pos.column + jump_to_pos.column) / 2;
-                self.board.clear_tile(jumped_row, jumped_col);
+            // a man passing the promotion row during the capture may already be a king
+            if self.rules().promotes_during_capture() {
+                let direction = self.current_player_info().direction;
+                let passed_promotion_row = the_move[1..].iter()
+                    .any(|position| self.rules().is_promotion_row(&self.board, direction, position.row));
+                if passed_promotion_row {
+                    self.coronate(final_position.row, final_position.column);
+                }
             }
 
-            let game_state = self.finish_move(
-                final_position.row, final_position.column);
+            let game_state = self.finish_move(&the_move, &captures, man_moved);
             Ok(game_state)
         } else {
-            Err(MoveError::InvalidMove)
+            let all_jump_moves = self.find_all_jump_moves();
+            let jump_legal = all_jump_moves.iter()
+                .any(|jump_tree| jump_tree.contains_complete_jump_sequence(&the_move));
+            let jump_started = the_move.len() > 1 && all_jump_moves.iter()
+                .any(|jump_tree| jump_tree.contains_jump_sequence(&the_move));
+            if jump_legal {
+                Err(MoveError::NotPriorityCapture)
+            } else if jump_started {
+                Err(MoveError::IncompleteJump)
+            } else {
+                Err(MoveError::InvalidMove)
+            }
         }
     }
 }
@@ -427,10 +904,17 @@ mod test {
     fn get_new_game() -> Game {
         Game::new("alice".into(),
                   "bob".into(),
-                  TokenBalance{ token_id: Some("NEAR".into()), balance: 1 }
+                  TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                  GameVariant::American
         )
     }
 
+    // replaces the man at the given position with a king of the same player
+    fn crown(game: &mut Game, row: usize, col: usize) {
+        game.board.crown(row, col);
+        game.find_available_moves();
+    }
+
     #[test]
     fn good_simple_move() {
         let mut game = get_new_game();
@@ -510,6 +994,27 @@ mod test {
         assert!(jumping_piece.is_some());
     }
 
+    #[test]
+    fn incomplete_multi_jump_move() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(3, 3)],
+            vec![
+                BoardPosition::new(4, 4),
+                BoardPosition::new(6, 4),
+                BoardPosition::new(2, 2)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)]);
+        let exp_result: Result<GameState, MoveError> = Err(MoveError::IncompleteJump);
+        assert_eq!(exp_result, result);
+
+        let player_id = game.current_player().id;
+        assert_eq!(1, player_id);
+
+        assert!(game.board().get_tile(3, 3).get_piece().is_some());
+        assert!(game.board().get_tile(4, 4).get_piece().is_some());
+    }
+
     #[test]
     fn bad_jump_move() {
         let mut game = get_new_game();
@@ -581,4 +1086,627 @@ mod test {
         let exp_result: Result<GameState, MoveError> = Ok(GameState::GameOver { winner_id: 1 });
         assert_eq!(exp_result, result);
     }
+
+    #[test]
+    fn draw_by_threefold_repetition() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 0);
+        crown(&mut game, 7, 7);
+
+        let moves = [(0, 0, 1, 1), (7, 7, 6, 6), (1, 1, 0, 0), (6, 6, 7, 7)];
+
+        // the position after the first move is repeated for the third time on the ninth move
+        for &(from_row, from_col, to_row, to_col) in moves.iter().cycle().take(8) {
+            let result = game.apply_simple_move(
+                SimpleMove::new(from_row, from_col, to_row, to_col));
+            assert_eq!(Ok(GameState::InProgress), result);
+        }
+
+        let result = game.apply_simple_move(SimpleMove::new(0, 0, 1, 1));
+        assert_eq!(Ok(GameState::Draw), result);
+    }
+
+    #[test]
+    fn repetitions_count_the_starting_position() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 0);
+        crown(&mut game, 7, 7);
+        let position = parse_fen(&game_to_fen(&game), GameVariant::American).ok().unwrap();
+        let mut game = Game::from_position(position, "alice".into(), "bob".into(),
+                                           TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                                           GameVariant::American);
+
+        let moves = [(0, 0, 1, 1), (7, 7, 6, 6), (1, 1, 0, 0), (6, 6, 7, 7)];
+
+        // the starting position comes back for the second time on the fourth move, and the third time on the eighth
+        for &(from_row, from_col, to_row, to_col) in moves.iter().cycle().take(7) {
+            let result = game.apply_simple_move(
+                SimpleMove::new(from_row, from_col, to_row, to_col));
+            assert_eq!(Ok(GameState::InProgress), result);
+        }
+
+        let result = game.apply_simple_move(SimpleMove::new(6, 6, 7, 7));
+        assert_eq!(Ok(GameState::Draw), result);
+    }
+
+    #[test]
+    fn accepted_draw_offer_ends_the_game() {
+        let mut game: GameToSave = get_new_game().into();
+        game.offer_draw(0);
+        assert_eq!(Some(0), game.draw_offer_index);
+
+        game.accept_draw(1);
+        assert!(game.is_draw);
+        assert!(game.is_finished());
+        assert_eq!(None, game.draw_offer_index);
+    }
+
+    #[test]
+    fn declined_draw_offer_can_be_made_again() {
+        let mut game: GameToSave = get_new_game().into();
+        game.offer_draw(1);
+        game.decline_draw(0);
+        assert!(!game.is_finished());
+        assert_eq!(None, game.draw_offer_index);
+
+        game.offer_draw(1);
+        assert_eq!(Some(1), game.draw_offer_index);
+    }
+
+    #[test]
+    #[should_panic(expected = "No draw offer from the opponent")]
+    fn own_draw_offer_cannot_be_accepted() {
+        let mut game: GameToSave = get_new_game().into();
+        game.offer_draw(0);
+        game.accept_draw(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Draw already offered")]
+    fn pending_draw_offer_cannot_be_repeated() {
+        let mut game: GameToSave = get_new_game().into();
+        game.offer_draw(0);
+        game.offer_draw(1);
+    }
+
+    #[test]
+    fn draw_after_moves_without_progress() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 0);
+        crown(&mut game, 7, 7);
+        game.moves_without_progress = DRAW_MOVES_WITHOUT_PROGRESS - 1;
+
+        let result = game.apply_simple_move(SimpleMove::new(0, 0, 1, 1));
+        assert_eq!(Ok(GameState::Draw), result);
+    }
+
+    #[test]
+    fn man_move_resets_moves_without_progress() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(7, 7)]);
+        crown(&mut game, 7, 7);
+        game.moves_without_progress = DRAW_MOVES_WITHOUT_PROGRESS - 1;
+
+        let result = game.apply_simple_move(SimpleMove::new(0, 0, 1, 1));
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert_eq!(0, game.moves_without_progress);
+
+        let result = game.apply_simple_move(SimpleMove::new(7, 7, 6, 6));
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert_eq!(1, game.moves_without_progress);
+    }
+
+    fn count_pieces(game: &Game, player_id: u32) -> usize {
+        let board = game.board();
+        (0..board.number_rows())
+            .flat_map(|r| (0..board.number_columns()).map(move |c| (r, c)))
+            .filter(|&(r, c)| board.get_tile(r, c).get_piece()
+                .map(|piece| piece.get_player_id() == player_id)
+                .unwrap_or(false))
+            .count()
+    }
+
+    #[test]
+    fn international_new_game() {
+        let game = Game::new("alice".into(),
+                             "bob".into(),
+                             TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                             GameVariant::International);
+
+        assert_eq!(10, game.board().number_rows());
+        assert_eq!(20, count_pieces(&game, 1));
+        assert_eq!(20, count_pieces(&game, 2));
+        assert_eq!(9, game.available_simple_moves.len());
+        assert!(game.available_jump_moves.is_empty());
+    }
+
+    #[test]
+    fn international_man_captures_backward() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::International,
+            vec![BoardPosition::new(5, 5)],
+            vec![BoardPosition::new(4, 4), BoardPosition::new(9, 1)]);
+
+        let result = game.apply_simple_move(SimpleMove::new(5, 5, 6, 6));
+        assert_eq!(Err(MoveError::ShouldHaveJumped), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(5, 5), BoardPosition::new(3, 3)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(4, 4).get_piece().is_none());
+    }
+
+    #[test]
+    fn international_flying_king_simple_moves() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::International,
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(9, 1)]);
+        crown(&mut game, 0, 0);
+
+        assert_eq!(9, game.available_simple_moves.len());
+        assert!(game.available_simple_moves.contains(&SimpleMove::new(0, 0, 9, 9)));
+
+        let result = util::apply_positions_as_move(
+            &mut game, vec![BoardPosition::new(0, 0), BoardPosition::new(6, 6)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(6, 6).get_piece().is_some());
+    }
+
+    #[test]
+    fn international_flying_king_captures_from_distance() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::International,
+            vec![BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(4, 4), BoardPosition::new(9, 1)]);
+        crown(&mut game, 0, 0);
+
+        // the king may land on any empty tile behind the captured piece
+        let jump_tree = &game.available_jump_moves[0];
+        assert_eq!(5, jump_tree.jumps().len());
+
+        let result = util::apply_positions_as_move(
+            &mut game, vec![BoardPosition::new(0, 0), BoardPosition::new(7, 7)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(4, 4).get_piece().is_none());
+        assert!(game.board().get_tile(7, 7).get_piece().is_some());
+    }
+
+    #[test]
+    fn international_majority_capture() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::International,
+            vec![BoardPosition::new(2, 2), BoardPosition::new(2, 6)],
+            vec![BoardPosition::new(3, 1), BoardPosition::new(3, 5), BoardPosition::new(5, 3)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 0)]);
+        assert_eq!(Err(MoveError::NotPriorityCapture), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 6), BoardPosition::new(4, 4), BoardPosition::new(6, 2)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert_eq!(1, count_pieces(&game, 2));
+    }
+
+    #[test]
+    fn russian_man_promoted_during_capture() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Russian,
+            vec![BoardPosition::new(5, 1)],
+            vec![BoardPosition::new(6, 2), BoardPosition::new(5, 5), BoardPosition::new(1, 7)]);
+
+        // after reaching the last row the piece must go on capturing as a king
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(5, 1), BoardPosition::new(7, 3)]);
+        assert_eq!(Err(MoveError::IncompleteJump), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(5, 1), BoardPosition::new(7, 3), BoardPosition::new(3, 7)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+
+        assert!(game.board().get_tile(6, 2).get_piece().is_none());
+        assert!(game.board().get_tile(5, 5).get_piece().is_none());
+        let piece_type = game.board().get_tile(3, 7).get_piece().unwrap().get_type();
+        match piece_type {
+            PieceType::King => {}
+            _ => panic!("Expected piece to be a King"),
+        }
+    }
+
+    #[test]
+    fn russian_man_captures_backward() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Russian,
+            vec![BoardPosition::new(4, 4)],
+            vec![BoardPosition::new(3, 3), BoardPosition::new(7, 1)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(4, 4), BoardPosition::new(2, 2)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(3, 3).get_piece().is_none());
+    }
+
+    #[test]
+    fn american_man_not_promoted_during_capture() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(5, 1)],
+            vec![BoardPosition::new(6, 2), BoardPosition::new(5, 5), BoardPosition::new(1, 7)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(5, 1), BoardPosition::new(7, 3)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(5, 5).get_piece().is_some());
+    }
+
+    #[test]
+    fn italian_man_cannot_capture_king() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Italian,
+            vec![BoardPosition::new(2, 2)],
+            vec![BoardPosition::new(3, 3), BoardPosition::new(7, 7)]);
+        crown(&mut game, 3, 3);
+
+        assert!(game.available_jump_moves.is_empty());
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 4)]);
+        assert_eq!(Err(MoveError::InvalidMove), result);
+    }
+
+    #[test]
+    fn italian_capture_most_pieces() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Italian,
+            vec![BoardPosition::new(2, 2), BoardPosition::new(2, 6)],
+            vec![BoardPosition::new(3, 1), BoardPosition::new(3, 5), BoardPosition::new(5, 3)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 0)]);
+        assert_eq!(Err(MoveError::NotPriorityCapture), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 6), BoardPosition::new(4, 4), BoardPosition::new(6, 2)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+    }
+
+    #[test]
+    fn italian_capture_with_king() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Italian,
+            vec![BoardPosition::new(2, 2), BoardPosition::new(2, 6)],
+            vec![BoardPosition::new(3, 1), BoardPosition::new(3, 5)]);
+        crown(&mut game, 2, 6);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 0)]);
+        assert_eq!(Err(MoveError::NotPriorityCapture), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 6), BoardPosition::new(4, 4)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+    }
+
+    #[test]
+    fn italian_capture_most_kings() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Italian,
+            vec![BoardPosition::new(4, 4)],
+            vec![BoardPosition::new(5, 5), BoardPosition::new(3, 3)]);
+        crown(&mut game, 4, 4);
+        crown(&mut game, 3, 3);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(4, 4), BoardPosition::new(6, 6)]);
+        assert_eq!(Err(MoveError::NotPriorityCapture), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(4, 4), BoardPosition::new(2, 2)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+    }
+
+    #[test]
+    fn italian_capture_king_first() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Italian,
+            vec![BoardPosition::new(2, 4)],
+            vec![BoardPosition::new(3, 5), BoardPosition::new(5, 5),
+                 BoardPosition::new(3, 3), BoardPosition::new(5, 1)]);
+        crown(&mut game, 2, 4);
+        crown(&mut game, 5, 5);
+        crown(&mut game, 3, 3);
+
+        // both sequences capture a man and a king, the king has to be taken first
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 4), BoardPosition::new(4, 6), BoardPosition::new(6, 4)]);
+        assert_eq!(Err(MoveError::NotPriorityCapture), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 4), BoardPosition::new(4, 2), BoardPosition::new(6, 0)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+    }
+
+    #[test]
+    fn turkish_new_game() {
+        let game = Game::new("alice".into(),
+                             "bob".into(),
+                             TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                             GameVariant::Turkish);
+
+        assert_eq!(16, count_pieces(&game, 1));
+        assert_eq!(16, count_pieces(&game, 2));
+        for c in 0..8 {
+            assert!(game.board().get_tile(0, c).get_piece().is_none());
+            assert_eq!(1, game.board().get_tile(1, c).get_piece().unwrap().get_player_id());
+            assert_eq!(2, game.board().get_tile(6, c).get_piece().unwrap().get_player_id());
+            assert!(game.board().get_tile(7, c).get_piece().is_none());
+        }
+
+        // the men of the front row move forward, the others are blocked
+        assert_eq!(8, game.available_simple_moves.len());
+    }
+
+    #[test]
+    fn turkish_man_moves_forward_and_sideways() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Turkish,
+            vec![BoardPosition::new(3, 3)],
+            vec![BoardPosition::new(7, 7)]);
+
+        assert_eq!(3, game.available_simple_moves.len());
+        assert_eq!(Err(MoveError::InvalidMove), game.apply_simple_move(SimpleMove::new(3, 3, 4, 4)));
+        assert_eq!(Err(MoveError::InvalidMove), game.apply_simple_move(SimpleMove::new(3, 3, 2, 3)));
+        assert_eq!(Ok(GameState::InProgress), game.apply_simple_move(SimpleMove::new(3, 3, 3, 2)));
+    }
+
+    #[test]
+    fn turkish_man_captures_sideways() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Turkish,
+            vec![BoardPosition::new(3, 3)],
+            vec![BoardPosition::new(3, 4), BoardPosition::new(7, 7)]);
+
+        assert_eq!(Err(MoveError::ShouldHaveJumped), game.apply_simple_move(SimpleMove::new(3, 3, 4, 3)));
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(3, 3), BoardPosition::new(3, 5)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert!(game.board().get_tile(3, 4).get_piece().is_none());
+    }
+
+    #[test]
+    fn turkish_flying_king_crosses_captured_squares() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Turkish,
+            vec![BoardPosition::new(0, 2)],
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 4), BoardPosition::new(3, 6),
+                 BoardPosition::new(2, 1), BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 2);
+
+        // captured pieces are removed at once, so the king passes (2, 2) again
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(0, 2), BoardPosition::new(4, 2), BoardPosition::new(4, 6),
+                 BoardPosition::new(2, 6), BoardPosition::new(2, 0)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        assert_eq!(1, count_pieces(&game, 2));
+    }
+
+    #[test]
+    fn turkish_flying_king_cannot_turn_back() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Turkish,
+            vec![BoardPosition::new(0, 4)],
+            vec![BoardPosition::new(0, 2), BoardPosition::new(0, 6), BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 4);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(0, 4), BoardPosition::new(0, 1), BoardPosition::new(0, 7)]);
+        assert_eq!(Err(MoveError::InvalidMove), result);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(0, 4), BoardPosition::new(0, 1)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+    }
+
+    #[test]
+    fn giveaway_player_without_pieces_wins() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Giveaway,
+            vec![BoardPosition::new(2, 2)],
+            vec![BoardPosition::new(3, 3)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(2, 2), BoardPosition::new(4, 4)]);
+        assert_eq!(Ok(GameState::GameOver { winner_id: 1 }), result);
+    }
+
+    #[test]
+    fn giveaway_blocked_player_wins() {
+        let mut game = Game::with_variant_and_piece_positions(
+            GameVariant::Giveaway,
+            vec![BoardPosition::new(5, 1), BoardPosition::new(6, 2), BoardPosition::new(5, 3)],
+            vec![BoardPosition::new(7, 1)]);
+
+        // the man on (7, 1) can neither move nor jump once (6, 0) is taken
+        let result = game.apply_simple_move(SimpleMove::new(5, 1, 6, 0));
+        assert_eq!(Ok(GameState::GameOver { winner_id: 1 }), result);
+    }
+
+    #[test]
+    fn moves_are_recorded() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(5, 1), BoardPosition::new(0, 0)],
+            vec![BoardPosition::new(6, 2), BoardPosition::new(3, 7)]);
+
+        let result = game.apply_jump_move(
+            vec![BoardPosition::new(5, 1), BoardPosition::new(7, 3)]);
+        assert_eq!(Ok(GameState::InProgress), result);
+        let result = game.apply_simple_move(SimpleMove::new(3, 7, 2, 6));
+        assert_eq!(Ok(GameState::InProgress), result);
+
+        assert_eq!(vec![
+            MoveRecord {
+                player_index: 0,
+                timestamp: 0,
+                positions: vec![(5, 1), (7, 3)],
+                captures: vec![(6, 2)],
+                promoted: true,
+            },
+            MoveRecord {
+                player_index: 1,
+                timestamp: 0,
+                positions: vec![(3, 7), (2, 6)],
+                captures: vec![],
+                promoted: false,
+            }], game.moves);
+    }
+
+    // plays a move the way make_move does, keeping the position before it
+    fn play(game: &mut Game, the_move: SimpleMove) {
+        game.charge_clock(true);
+        game.save_takeback_position();
+        assert_eq!(Ok(GameState::InProgress), game.apply_simple_move(the_move));
+        game.turns += 1;
+    }
+
+    #[test]
+    fn takebacks() {
+        let mut game = get_new_game();
+        let start = game_to_fen(&game);
+        play(&mut game, SimpleMove::new(2, 0, 3, 1));
+        let after_first_move = game_to_fen(&game);
+        play(&mut game, SimpleMove::new(5, 1, 4, 0));
+
+        // the second player takes back their reply, the clock of the first player kept running
+        game.block_timestamp = 10;
+        game.take_back(1);
+        assert_eq!(after_first_move, game_to_fen(&game));
+        assert_eq!(1, game.current_player_index);
+        assert_eq!(1, game.turns);
+        assert_eq!(1, game.moves.len());
+        assert_eq!(1, game.moves_count);
+        assert_eq!(vec![10, 0], game.total_time_spent);
+        assert_eq!(10, game.last_turn_timestamp);
+
+        // the first player takes back their move, and the reply with it
+        play(&mut game, SimpleMove::new(5, 3, 4, 2));
+        game.take_back(0);
+        assert_eq!(start, game_to_fen(&game));
+        assert_eq!(0, game.current_player_index);
+        assert_eq!(0, game.turns);
+        assert!(game.moves.is_empty());
+        assert_eq!(0, game.moves_count);
+        assert!(game.takeback_positions.is_empty());
+        assert!(game.available_simple_moves.contains(&SimpleMove::new(2, 0, 3, 1)));
+    }
+
+    #[test]
+    fn takebacks_restore_the_position_history() {
+        let mut game = Game::with_piece_positions(
+            vec![BoardPosition::new(0, 0), BoardPosition::new(2, 2)],
+            vec![BoardPosition::new(7, 7)]);
+        crown(&mut game, 0, 0);
+        crown(&mut game, 7, 7);
+        play(&mut game, SimpleMove::new(0, 0, 1, 1));
+        play(&mut game, SimpleMove::new(7, 7, 6, 6));
+        let history = game.position_history.clone();
+
+        // king moves keep the history, which the takeback cuts back
+        play(&mut game, SimpleMove::new(1, 1, 0, 0));
+        play(&mut game, SimpleMove::new(6, 6, 7, 7));
+        game.take_back(0);
+        assert_eq!(history, game.position_history);
+        assert_eq!(2, game.moves_without_progress);
+
+        // a man move clears it, the history starts again from the position taken back
+        play(&mut game, SimpleMove::new(2, 2, 3, 3));
+        assert_eq!(1, game.position_history.len());
+        game.take_back(0);
+        assert_eq!(vec![game.position_key()], game.position_history);
+        assert_eq!(2, game.moves_without_progress);
+    }
+
+    #[test]
+    fn only_the_last_moves_are_kept() {
+        let mut game = get_new_game();
+        play(&mut game, SimpleMove::new(2, 0, 3, 1));
+        play(&mut game, SimpleMove::new(5, 1, 4, 0));
+        play(&mut game, SimpleMove::new(2, 2, 3, 3));
+        assert_eq!(vec![1, 0], game.takeback_positions.iter()
+            .map(|position| position.player_index)
+            .collect::<Vec<_>>());
+
+        let mut game = get_new_game();
+        game.takebacks_allowed = false;
+        play(&mut game, SimpleMove::new(2, 0, 3, 1));
+        assert!(game.takeback_positions.is_empty());
+    }
+
+    #[test]
+    fn clocks() {
+        const SECOND: Timestamp = 1_000_000_000;
+        let mut game = get_new_game();
+        game.time_control = TimeControl::Clock(
+            ClockControl { initial_seconds: 60, bonus: Some(TimeBonus::Increment(2)), max_move_seconds: None });
+        game.remaining_time = vec![60 * SECOND; 2];
+
+        game.block_timestamp = 10 * SECOND;
+        assert_eq!(50 * SECOND, game.remaining_time(0));
+        assert_eq!(60 * SECOND, game.remaining_time(1));
+        play(&mut game, SimpleMove::new(2, 0, 3, 1));
+        assert_eq!(vec![52 * SECOND, 60 * SECOND], game.remaining_time);
+        assert_eq!(vec![10 * SECOND, 0], game.total_time_spent);
+
+        game.block_timestamp = 70 * SECOND;
+        assert_eq!(70 * SECOND, game.move_deadline());
+        assert!(!game.is_out_of_time());
+        assert_eq!(0, game.remaining_time(1));
+        game.block_timestamp += 1;
+        assert!(game.is_out_of_time());
+
+        // taking the move back takes its increment back, not the time spent on it
+        game.block_timestamp = 15 * SECOND;
+        game.take_back(0);
+        assert_eq!(vec![50 * SECOND, 55 * SECOND], game.remaining_time);
+        assert_eq!(vec![10 * SECOND, 5 * SECOND], game.total_time_spent);
+        assert_eq!(15 * SECOND, game.last_turn_timestamp);
+    }
+
+    #[test]
+    fn correspondence_clocks() {
+        const DAY: Timestamp = 24 * ONE_HOUR;
+        let mut game = get_new_game();
+        game.time_control = TimeControl::Correspondence(CorrespondenceControl { days_per_move: 3, vacation_days: 2 });
+        game.remaining_time = vec![2 * DAY; 2];
+        assert_eq!(3 * DAY, game.move_deadline());
+
+        // a move in time keeps the vacation days in the bank
+        game.block_timestamp = 3 * DAY;
+        play(&mut game, SimpleMove::new(2, 0, 3, 1));
+        assert_eq!(vec![2 * DAY, 2 * DAY], game.remaining_time);
+        assert_eq!(6 * DAY, game.move_deadline());
+
+        // a vacation day taken pushes the deadline back
+        game.block_timestamp = 5 * DAY;
+        game.take_vacation(1);
+        assert_eq!(DAY, game.remaining_time(1));
+        assert_eq!(7 * DAY, game.move_deadline());
+        game.block_timestamp = 6 * DAY + DAY / 2;
+        assert!(!game.is_out_of_time());
+        game.block_timestamp = 7 * DAY + 1;
+        assert!(game.is_out_of_time());
+
+        // the vacation ends with the move
+        game.block_timestamp = 7 * DAY;
+        play(&mut game, SimpleMove::new(5, 1, 4, 0));
+        assert_eq!(vec![2 * DAY, DAY], game.remaining_time);
+        assert_eq!(0, game.vacation_time);
+        assert_eq!(10 * DAY, game.move_deadline());
+    }
 }
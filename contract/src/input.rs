@@ -1,4 +1,5 @@
 use crate::board::BoardPosition;
+use crate::rules::RuleSet;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum InputError {
@@ -11,15 +12,19 @@ pub enum TokenError {
 	MissingFile { token : String },
 	MissingRank { token : String },
 	ZeroRank { token : String },
-	InvalidCharacter { token : String, char_index : usize }
+	InvalidCharacter { token : String, char_index : usize },
+	OutOfBounds { token : String },
+	InvalidSquareNumber { token : String }
 }
 
 ///
-/// Parse a move from a string
+/// Parse a move from a string, either board positions separated by
+/// whitespace (`c3 d4`) or square numbers of the variant separated by
+/// `-` or `x` (`11-15`, `22x15x6`)
 ///
-pub fn parse_move(the_move : &str) -> Result<Vec<BoardPosition>, InputError> {
-	let results : Vec<_> = the_move.split_whitespace()
-		.map(token_validator)
+pub fn parse_move(the_move : &str, rules : &dyn RuleSet) -> Result<Vec<BoardPosition>, InputError> {
+	let results : Vec<_> = split_tokens(the_move).into_iter()
+		.map(|token| token_validator(token, rules))
 		.collect();
 
 	let (ok_iter, err_iter) : (Vec<_>, Vec<_>) = results.into_iter()
@@ -50,12 +55,47 @@ pub fn parse_move(the_move : &str) -> Result<Vec<BoardPosition>, InputError> {
 	Ok(positions)
 }
 
+//
+// Split a move into its tokens. Whitespace and `-` separate tokens,
+// `x` does too when it follows a digit, as other letters are files.
+//
+fn split_tokens(the_move : &str) -> Vec<&str> {
+	let mut tokens = Vec::new();
+	let mut start = 0;
+	let mut previous = ' ';
+
+	for (index, ch) in the_move.char_indices() {
+		let is_separator = ch.is_whitespace() || ch == '-'
+			|| ((ch == 'x' || ch == 'X') && previous.is_ascii_digit());
+		if is_separator {
+			if start < index {
+				tokens.push(&the_move[start..index]);
+			}
+			start = index + ch.len_utf8();
+		}
+		previous = ch;
+	}
+
+	if start < the_move.len() {
+		tokens.push(&the_move[start..]);
+	}
+
+	tokens
+}
+
 //
 // Determines whether a position string is valid.
-// Expects a strict sequence of alphabetic characters (rank)
-// followed by a sequence of numeric characters (file).
+// Expects either a square number, or a strict sequence of alphabetic
+// characters (file) followed by a sequence of numeric characters (rank)
+// within the board.
 //
-fn token_validator(token : &str) -> Result<BoardPosition, TokenError> {
+fn token_validator(token : &str, rules : &dyn RuleSet) -> Result<BoardPosition, TokenError> {
+	if token.chars().all(|ch| ch.is_ascii_digit()) {
+		return token.parse::<usize>().ok()
+			.and_then(|number| rules.square_position(number))
+			.ok_or_else(|| TokenError::InvalidSquareNumber { token : token.to_string() });
+	}
+
 	let parse_file_rank_result = parse_file_rank(token);
 	match parse_file_rank_result {
 		Ok((file, rank)) => {
@@ -66,12 +106,15 @@ fn token_validator(token : &str) -> Result<BoardPosition, TokenError> {
 				return Err(TokenError::MissingRank { token : token.to_string() });
 			}
 
-			let row : usize = rank.parse::<usize>().unwrap();
+			let row : usize = rank.parse::<usize>().unwrap_or(usize::MAX);
 			let col : usize = file_to_row_position(&file);
 
 			if row == 0 {
 				return Err(TokenError::ZeroRank { token : token.to_string() });
 			}
+			if row > rules.board_size() || col > rules.board_size() {
+				return Err(TokenError::OutOfBounds { token : token.to_string() });
+			}
 
 			Ok(BoardPosition::new(row - 1, col - 1))
 		},
@@ -103,7 +146,7 @@ fn parse_file_rank(token : &str) -> Result<(String, String), TokenError> {
 				if ch.is_alphabetic() {
 					file.push(ch);
 					char_opt = iter.next();
-				} else if ch.is_numeric() {
+				} else if ch.is_ascii_digit() {
 					parse_state = ParseState::Rank;
 				} else {
 					return Err(TokenError::InvalidCharacter {
@@ -111,7 +154,7 @@ fn parse_file_rank(token : &str) -> Result<(String, String), TokenError> {
 				}
 			}
 			ParseState::Rank => {
-				if ch.is_numeric() {
+				if ch.is_ascii_digit() {
 					rank.push(ch);
 					char_opt = iter.next();
 				} else {
@@ -133,7 +176,7 @@ fn file_to_row_position(file : &str) -> usize {
 	let alphabet_length = 26;
 
 	for c in file.chars() {
-		row = row * alphabet_length + char_to_position(c);
+		row = row.saturating_mul(alphabet_length).saturating_add(char_to_position(c));
 	}
 
 	row
@@ -141,7 +184,7 @@ fn file_to_row_position(file : &str) -> usize {
 
 //
 // Convert a single alphabetic character to number
-// Case insensitive [a-z] -> [1-26]
+// Case insensitive [a-z] -> [1-26], other letters are off the board
 //
 fn char_to_position( c : char ) -> usize {
 	debug_assert!(c.is_alphabetic());
@@ -149,6 +192,52 @@ fn char_to_position( c : char ) -> usize {
 	match c {
 		'A'..='Z' => (c as usize) - ('A' as usize) + 1,
 		'a'..='z' => (c as usize) - ('a' as usize) + 1,
-		_ => unreachable!()
+		_ => usize::MAX
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::rules::{GameVariant, AmericanRules};
+
+	#[test]
+	fn algebraic_notation() {
+		assert_eq!(Ok(vec![BoardPosition::new(2, 2), BoardPosition::new(3, 3)]),
+			parse_move("c3 d4", &AmericanRules));
+		assert_eq!(Err(InputError::TooFewTokens), parse_move("c3", &AmericanRules));
+	}
+
+	#[test]
+	fn numeric_notation() {
+		let square = |number| AmericanRules.square_position(number).unwrap();
+
+		assert_eq!(Ok(vec![square(11), square(15)]), parse_move("11-15", &AmericanRules));
+		assert_eq!(Ok(vec![square(22), square(15), square(6)]), parse_move("22x15x6", &AmericanRules));
+		assert_eq!(Ok(vec![BoardPosition::new(2, 2), square(18)]), parse_move("c3X18", &AmericanRules));
+
+		let international = GameVariant::International.rules();
+		assert_eq!(Ok(vec![international.square_position(32).unwrap(), international.square_position(28).unwrap()]),
+			parse_move("32-28", international));
+	}
+
+	#[test]
+	fn invalid_squares() {
+		let token_errors = |the_move| match parse_move(the_move, &AmericanRules) {
+			Err(InputError::InvalidTokens { tokens }) => tokens,
+			_ => Vec::new()
+		};
+
+		assert_eq!(vec![TokenError::InvalidSquareNumber { token : "33".to_string() },
+				TokenError::InvalidSquareNumber { token : "0".to_string() }],
+			token_errors("33-0"));
+		assert_eq!(vec![TokenError::OutOfBounds { token : "i1".to_string() },
+				TokenError::OutOfBounds { token : "a9".to_string() }],
+			token_errors("i1 a9"));
+		assert_eq!(vec![TokenError::OutOfBounds { token : "x3".to_string() },
+				TokenError::OutOfBounds { token : "b99999999999999999999".to_string() }],
+			token_errors("x3 b99999999999999999999"));
+		assert_eq!(vec![TokenError::InvalidCharacter { token : "c3+".to_string(), char_index : 2 }],
+			token_errors("c3+ d4"));
 	}
 }
\ No newline at end of file
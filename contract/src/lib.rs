@@ -1,6 +1,6 @@
 use near_sdk::{AccountId, Balance, BorshStorageKey, env, log, near_bindgen, PanicOnDefault, setup_alloc, Timestamp};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::json_types::U128;
 pub use ai::{
@@ -11,17 +11,25 @@ pub use ai::{
     find_simple_moves_for_man,
     JumpMove,
     SimpleMove};
-pub use board::{Board, BoardPosition};
+pub use ai::eval::{EvalWeights, evaluate_position, Evaluation};
+pub use ai::perft::{perft, perft_divide};
+pub use ai::search::{find_best_move, Search, SearchLimits, SearchMove, SearchResult};
+pub use ai::tablebase::{Tablebase, TablebaseProbe, TablebaseResult};
+pub use board::{Board, BoardPosition, BoardToSave, PackedBoard};
 pub use display::print_board;
-pub use game::{Game, GameState, MoveError};
+pub use fen::{board_to_fen, FenError, FenPosition, game_to_fen, parse_fen};
+pub use game::{Game, GameState, MoveError, MoveRecord};
 pub use input::{InputError, parse_move, TokenError};
+pub use pdn::{game_to_pdn, PdnError, replay_pdn};
 pub use piece::{KingPiece, ManPiece, Piece, PieceType};
 pub use player::Player;
+pub use rules::{AmericanRules, GameVariant, RuleSet};
 pub use tile::{EmptyTile, OccupiedTile, Tile};
-pub use token_interfaces::WhitelistedToken;
+pub use time_control::{ClockControl, CorrespondenceControl, TimeBonus, TimeControl};
+pub use token_interfaces::{FtGameOptions, WhitelistedToken};
 
 
-use crate::game::GameToSave;
+use crate::game::{GameToSave, GameToSaveV1, VGameToSave};
 use crate::manager::*;
 
 use crate::token_interfaces::{ONE_YOCTO, yoctoToToken};
@@ -29,24 +37,36 @@ use crate::token_interfaces::{ONE_YOCTO, yoctoToToken};
 mod ai;
 mod board;
 mod display;
+mod fen;
 mod game;
 mod input;
 mod piece;
 mod player;
+mod rules;
 mod tile;
+mod time_control;
 mod util;
 mod manager;
+mod bot;
+mod pdn;
 mod token_interfaces;
 
 type GameId = u64;
 
 // 0.01 NEAR
 const MIN_DEPOSIT_NEAR: Balance = 10_000_000_000_000_000_000_000;
+// 0.1 NEAR, the largest stake matched by the house pool until the owner sets another one
+const DEFAULT_MAX_BOT_STAKE: Balance = 100_000_000_000_000_000_000_000;
 
+// the time each player has for the whole game, unless the game config sets another one
 const ONE_HOUR: Timestamp = 3_600_000_000_000;
 
+// 40 moves by each player without a capture or a man move
+const DRAW_MOVES_WITHOUT_PROGRESS: u32 = 80;
+const DRAW_REPETITIONS: usize = 3;
+
 const CHECKERBOARD_SIZE: usize = 8;
-const CHECKERS_NUMBER_TILES: usize = CHECKERBOARD_SIZE * CHECKERBOARD_SIZE;
+const INTERNATIONAL_BOARD_SIZE: usize = 10;
 
 setup_alloc!();
 
@@ -59,13 +79,20 @@ enum StorageKey {
     Affiliates {account_id: AccountId},
     TotalRewards {account_id: AccountId},
     TotalAffiliateRewards{ account_id: AccountId},
-    WhitelistedTokens
+    WhitelistedTokens,
+    VersionedGames,
+    GameMoves,
+    MovesOfGame {game_id: GameId},
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Checkers {
-    games: LookupMap<GameId, GameToSave>,
+    games: LookupMap<GameId, VGameToSave>,
+    // games saved before the migration to versioned games
+    legacy_games: LookupMap<GameId, GameToSaveV1>,
+    // the moves of each game, in the order they were made
+    game_moves: LookupMap<GameId, Vector<MoveRecord>>,
     available_players: UnorderedMap<AccountId, VGameConfig>,
     stats: UnorderedMap<AccountId, VStats>,
     available_games: UnorderedMap<GameId, (AccountId, AccountId)>,
@@ -73,6 +100,10 @@ pub struct Checkers {
 
     next_game_id: GameId,
     service_fee: Balance,
+    // funded by the owner, matches the stakes of games against the bot
+    house_pool: Balance,
+    // the largest stake the house pool matches in a game against the bot
+    max_bot_stake: Balance,
 }
 
 #[near_bindgen]
@@ -80,7 +111,9 @@ impl Checkers {
     #[init]
     pub fn new() -> Self {
         Self {
-            games: LookupMap::new(StorageKey::Games),
+            games: LookupMap::new(StorageKey::VersionedGames),
+            legacy_games: LookupMap::new(StorageKey::Games),
+            game_moves: LookupMap::new(StorageKey::GameMoves),
             available_players: UnorderedMap::new(StorageKey::AvailablePlayers),
             stats: UnorderedMap::new(StorageKey::Stats),
             available_games: UnorderedMap::new(StorageKey::AvailableGames),
@@ -88,8 +121,48 @@ impl Checkers {
 
             next_game_id: 0,
             service_fee: 0,
+            house_pool: 0,
+            max_bot_stake: DEFAULT_MAX_BOT_STAKE,
         }
     }
+
+    /// Moves the games to versioned storage and adds the house pool, its
+    /// maximum stake and the storage of the moves. The games saved before
+    /// keep their layout and are converted when they are saved again.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("Contract is not initialized");
+        let old_state = CheckersV1::try_from_slice(&state).expect("Unknown contract state");
+
+        Self {
+            games: LookupMap::new(StorageKey::VersionedGames),
+            legacy_games: old_state.games,
+            game_moves: LookupMap::new(StorageKey::GameMoves),
+            available_players: old_state.available_players,
+            stats: old_state.stats,
+            available_games: old_state.available_games,
+            whitelisted_tokens: old_state.whitelisted_tokens,
+
+            next_game_id: old_state.next_game_id,
+            service_fee: old_state.service_fee,
+            house_pool: 0,
+            max_bot_stake: DEFAULT_MAX_BOT_STAKE,
+        }
+    }
+}
+
+// Contract state before games were versioned
+#[derive(BorshDeserialize)]
+struct CheckersV1 {
+    games: LookupMap<GameId, GameToSaveV1>,
+    available_players: UnorderedMap<AccountId, VGameConfig>,
+    stats: UnorderedMap<AccountId, VStats>,
+    available_games: UnorderedMap<GameId, (AccountId, AccountId)>,
+    whitelisted_tokens: UnorderedMap<AccountId, WhitelistedToken>,
+
+    next_game_id: GameId,
+    service_fee: Balance,
 }
 
 #[near_bindgen]
@@ -115,8 +188,16 @@ impl Checkers {
         let account_id: &AccountId = &env::predecessor_account_id();
         assert!(self.available_players.get(account_id).is_none(), "Already in the waiting list the list");
 
-        let deposit: Balance = env::attached_deposit();
+        let deposit: Balance = util::attached_deposit();
         assert!(deposit >= MIN_DEPOSIT_NEAR, "Deposit is too small. Attached: {}, Required: {}", deposit, MIN_DEPOSIT_NEAR);
+        config.time_control.assert_valid();
+
+        if config.vs_bot {
+            self.internal_check_if_has_game_started(account_id);
+            self.internal_add_referral(config.token_id.clone(), account_id, &referrer_id);
+            self.internal_start_bot_game(account_id, deposit, config);
+            return;
+        }
 
             self.available_players.insert(account_id,
                 &VGameConfig::Current(GameConfig {
@@ -124,17 +205,23 @@ impl Checkers {
                     deposit: Some(deposit),
                     first_move: config.first_move,
                     opponent_id: config.opponent_id,
+                    variant: config.variant,
+                    vs_bot: false,
+                    disable_takebacks: config.disable_takebacks,
+                    time_control: config.time_control,
             }));
 
         self.internal_check_if_has_game_started(&account_id);
         self.internal_add_referral(config.token_id, account_id, &referrer_id);
     }
     //calls in cross-contract transfer into checkers app
-    pub fn make_available_ft(&mut self, sender_id: AccountId, amount: U128, referrer_id: Option<AccountId>) -> bool{
+    pub fn make_available_ft(&mut self, sender_id: AccountId, amount: U128, referrer_id: Option<AccountId>,
+                             options: FtGameOptions) -> bool{
         let token_id = env::predecessor_account_id();
         let amount = amount.0;
         //fix - many times deposits before game started
         assert!(self.available_players.get(&sender_id.clone()).is_none(), "Already in the waiting list the list");
+        options.time_control.assert_valid();
         //get token data
         let is_token_whitelisted = self.is_whitelisted_token(token_id.clone());
         if is_token_whitelisted {
@@ -148,6 +235,10 @@ impl Checkers {
                         deposit: Some(amount),
                         first_move: FirstMoveOptions::Random,
                         opponent_id: None,
+                        variant: options.variant,
+                        vs_bot: false,
+                        disable_takebacks: options.disable_takebacks,
+                        time_control: options.time_control,
                     }));
 
             log!("Success deposit from @{} with {} ${} ", sender_id.clone(), yoctoToToken(amount, decimals), ticker);
@@ -185,13 +276,10 @@ impl Checkers {
             assert!(self.available_players.get(&account_id).is_some(), "You are not in available players list!");
             assert_ne!(account_id.clone(), opponent_id.clone(), "Find a friend to play");
 
-            // Get predecessor's available deposit
-            let predecessor_deposit:u128 = if self.available_players.get(&account_id).is_some() {
-                let predecessor_config: GameConfig = self.available_players.get(&account_id).unwrap().into();
-                predecessor_config.deposit.unwrap_or(0)
-            } else {
-                0u128
-            };
+            // Get predecessor's available deposit, variant, takeback option and time control
+            let predecessor_config: GameConfig = self.available_players.get(&account_id).unwrap().into();
+            let predecessor_deposit = predecessor_config.deposit.unwrap_or(0);
+            let predecessor_variant = predecessor_config.variant;
 
             self.internal_check_if_has_game_started(&account_id);
 
@@ -215,20 +303,41 @@ impl Checkers {
                 config.deposit.unwrap_or(0)
             );
 
+            // Both players must play the same variant
+            assert_eq!(
+                predecessor_variant,
+                config.variant,
+                "Mismatched variants for players! You: {:?}, Opponent {:?}",
+                predecessor_variant,
+                config.variant
+            );
+            let variant = config.variant;
+
+            // and the same time control
+            assert_eq!(
+                predecessor_config.time_control,
+                config.time_control,
+                "Mismatched time controls for players! You: {:?}, Opponent {:?}",
+                predecessor_config.time_control,
+                config.time_control
+            );
+
             log!("game reward:  token {:?} ", reward.token_id.clone());
             
             let token_id = reward.token_id.clone();
-            let game_to_save =
+            let mut game_to_save =
                 match config.first_move {
                     FirstMoveOptions::First => GameToSave::new(
                         account_id.clone(),
                         opponent_id.clone(),
-                        reward),
+                        reward,
+                        variant),
 
                     FirstMoveOptions::Second => GameToSave::new(
                         opponent_id.clone(),
                         account_id.clone(),
-                        reward),
+                        reward,
+                        variant),
 
                     FirstMoveOptions::Random => {
                         let seed = near_sdk::env::random_seed();
@@ -236,16 +345,20 @@ impl Checkers {
                             0 => GameToSave::new(
                                 opponent_id.clone(),
                                 account_id.clone(),
-                                reward),
+                                reward,
+                                variant),
                             _ => GameToSave::new(
                                 account_id.clone(),
                                 opponent_id.clone(),
-                                reward)
+                                reward,
+                                variant)
                         }
                     }
                 };
+            game_to_save.takebacks_allowed = !(config.disable_takebacks || predecessor_config.disable_takebacks);
+            game_to_save.set_time_control(config.time_control);
 
-            self.games.insert(&game_id, &game_to_save);
+            self.internal_save_game(&game_id, game_to_save);
 
             self.available_games.insert(&game_id, &(account_id.clone(), opponent_id.clone()));
 
@@ -273,9 +386,9 @@ impl Checkers {
     #[payable]
     pub fn give_up(&mut self, game_id: GameId) {
         //assert one yocto
-        assert_eq!(env::attached_deposit(), ONE_YOCTO, "Attach 1 yocto");
+        assert_eq!(util::attached_deposit(), ONE_YOCTO, "Attach 1 yocto");
         let mut game: GameToSave = self.internal_get_game(&game_id);
-        assert!(game.winner_index.is_none(), "Game already finished");
+        assert!(!game.is_finished(), "Game already finished");
         let account_id = env::predecessor_account_id();
 
         let player_1 = game.player_1.account_id.clone();
@@ -289,125 +402,508 @@ impl Checkers {
 
         self.internal_distribute_reward(&game.reward, &winner_account);
         game.winner_index = Some(winner_index);
-        self.games.insert(&game_id, &game);
+        self.internal_save_game(&game_id, game);
 
         self.internal_stop_game(game_id);
     }
 
     pub fn make_move(&mut self, game_id: GameId, line: String) {
         let mut game: Game = self.internal_get_game(&game_id).into();
-        assert!(game.winner_index.is_none(), "Game already finished");
+        assert!(!game.is_finished(), "Game already finished");
 
-        let mut update_game = false;
         let active_player = game.current_player_account_id();
         assert_eq!(active_player, env::predecessor_account_id(), "No access");
 
+        // a move made too late loses the game on time
+        if game.is_out_of_time() {
+            self.internal_finish_on_time(game_id, &mut game);
+            self.internal_save_played_game(&game_id, game);
+            return;
+        }
+        game.charge_clock(true);
+
         // display::print_board(game.board());
 
-        let parse_result = input::parse_move(&line);
+        let positions = match input::parse_move(&line, game.rules()) {
+            Ok(positions) => positions,
+            Err(e) => panic!("\n *** {}", util::input_error_message(&e))
+        };
+        game.save_takeback_position();
+        let mut game_state = match util::apply_positions_as_move(&mut game, positions) {
+            Ok(game_state) => game_state,
+            Err(e) => panic!("\n *** {}", util::move_error_message(&e))
+        };
+        game.turns += 1;
+        game.draw_offer_index = None;
+        game.takeback_request_index = None;
+
+        // the bot answers in the same transaction
+        if game_state == GameState::InProgress && game.current_player_account_id() == env::current_account_id() {
+            game_state = self.internal_make_bot_move(&mut game);
+        }
 
-        match parse_result {
-            Ok(positions) => {
-                let move_result = util::apply_positions_as_move(&mut game, positions);
-                match move_result {
-                    Ok(game_state) => match game_state {
-                        GameState::InProgress => {
-                            update_game = true;
-                        }
-                        GameState::GameOver { winner_id: winner_index } => {
-                            let winner_account = game.players[winner_index].account_id.clone();
-                            self.internal_distribute_reward(&game.reward, &winner_account);
-                            game.winner_index = Some(winner_index);
+        self.internal_finish_turn(game_id, &mut game, game_state);
 
-                            self.internal_stop_game(game_id);
+        // display::print_board(game.board());
+        self.internal_save_played_game(&game_id, game);
+    }
 
-                            update_game = true;
+    /// Offers the opponent to finish the game in a draw.
+    /// The offer stays until the opponent accepts or declines it, or until the next move.
+    pub fn offer_draw(&mut self, game_id: GameId) {
+        let mut game: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game.is_finished(), "Game already finished");
+        let player_index = game.player_index(&env::predecessor_account_id());
 
-                            log!("\nGame over! {} won!", winner_account);
-                        }
-                    },
-                    Err(e) => match e {
-                        MoveError::InvalidMove => panic!("\n *** Illegal move"),
-                        MoveError::ShouldHaveJumped => panic!("\n *** Must take jump")
-                    }
-                }
-            }
-            Err(e) => match e {
-                InputError::TooFewTokens =>
-                    panic!("\n *** You must specify at least two board positions"),
-                InputError::InvalidTokens { tokens: errors } => {
-                    for error in errors {
-                        match error {
-                            TokenError::MissingFile { token } =>
-                                panic!("\n *** Board position '{}' must specify file", token),
-                            TokenError::MissingRank { token } =>
-                                panic!("\n *** Board position '{}' must specify rank", token),
-                            TokenError::ZeroRank { token } =>
-                                panic!("\n *** Rank cannot be zero: {}", token),
-                            TokenError::InvalidCharacter { token, char_index } => {
-                                let ch = token.chars().nth(char_index).unwrap();
-                                panic!("\n *** Board position '{}' contains invalid character '{}'", token, ch);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        game.offer_draw(player_index);
+        self.internal_save_game(&game_id, game);
 
-        if update_game {
-            // display::print_board(game.board());
-            game.turns += 1;
-            let game_to_save: GameToSave = game.into();
-            self.games.insert(&game_id, &game_to_save);
-        }
+        log!("{} offers a draw", env::predecessor_account_id());
+    }
+
+    /// Accepts the draw offered by the opponent. Stakes are returned to both players.
+    #[payable]
+    pub fn accept_draw(&mut self, game_id: GameId) {
+        assert_eq!(util::attached_deposit(), ONE_YOCTO, "Attach 1 yocto");
+        let mut game: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game.is_finished(), "Game already finished");
+        let player_index = game.player_index(&env::predecessor_account_id());
+
+        game.accept_draw(player_index);
+
+        let players = [game.player_1.account_id.clone(), game.player_2.account_id.clone()];
+        self.internal_distribute_draw(&game.reward, &players);
+        self.internal_save_game(&game_id, game);
+
+        self.internal_stop_game(game_id);
+
+        log!("\nGame over! Draw");
+    }
+
+    pub fn decline_draw(&mut self, game_id: GameId) {
+        let mut game: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game.is_finished(), "Game already finished");
+        let player_index = game.player_index(&env::predecessor_account_id());
+
+        game.decline_draw(player_index);
+        self.internal_save_game(&game_id, game);
+    }
+
+    /// Asks the opponent to take back the last move of the caller, and their
+    /// reply if they made one. The request stays until the opponent accepts
+    /// or declines it, or until the next move.
+    pub fn request_takeback(&mut self, game_id: GameId) {
+        let mut game: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game.is_finished(), "Game already finished");
+        let player_index = game.player_index(&env::predecessor_account_id());
+        assert!(game.takebacks_allowed, "Takebacks are disabled in this game");
+        assert!(game.takeback_request_index.is_none(), "Takeback already requested");
+        assert!(game.can_take_back(player_index), "No move to take back");
+
+        game.takeback_request_index = Some(player_index);
+        self.internal_save_game(&game_id, game);
+
+        log!("{} requests a takeback", env::predecessor_account_id());
+    }
+
+    /// Accepts the takeback requested by the opponent: the position before
+    /// their last move comes back, and it is their turn again
+    pub fn accept_takeback(&mut self, game_id: GameId) {
+        let game_to_save: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game_to_save.is_finished(), "Game already finished");
+        let player_index = game_to_save.player_index(&env::predecessor_account_id());
+        assert_eq!(game_to_save.takeback_request_index, Some(1 - player_index), "No takeback request from the opponent");
+
+        let mut game: Game = game_to_save.into();
+        game.take_back(1 - player_index);
+        self.internal_save_played_game(&game_id, game);
+
+        log!("{} accepts the takeback", env::predecessor_account_id());
+    }
+
+    pub fn decline_takeback(&mut self, game_id: GameId) {
+        let mut game: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game.is_finished(), "Game already finished");
+        let player_index = game.player_index(&env::predecessor_account_id());
+        assert_eq!(game.takeback_request_index, Some(1 - player_index), "No takeback request from the opponent");
+
+        game.takeback_request_index = None;
+        self.internal_save_game(&game_id, game);
     }
 
     fn internal_stop_game(&mut self, game_id: GameId) {
         self.available_games.remove(&game_id);
     }
 
-    pub fn stop_game(&mut self, game_id: GameId) {
-        let mut game: GameToSave = self.internal_get_game(&game_id);
-        assert!(game.winner_index.is_none(), "Game already finished");
+    // Pays the stakes when the last move ended the game
+    pub(crate) fn internal_finish_turn(&mut self, game_id: GameId, game: &mut Game, game_state: GameState) {
+        match game_state {
+            GameState::InProgress => {}
+            GameState::GameOver { winner_id: winner_index } => {
+                let winner_account = game.players[winner_index].account_id.clone();
+                self.internal_distribute_reward(&game.reward, &winner_account);
+                game.winner_index = Some(winner_index);
 
-        let account_id = env::predecessor_account_id();
+                self.internal_stop_game(game_id);
 
-        let player_1 = game.player_1.account_id.clone();
-        let player_2 = game.player_2.account_id.clone();
+                log!("\nGame over! {} won!", winner_account);
+            }
+            GameState::Draw => {
+                let players = [
+                    game.players[0].account_id.clone(),
+                    game.players[1].account_id.clone()];
+                self.internal_distribute_draw(&game.reward, &players);
+                game.is_draw = true;
 
-        let (winner_index, winner_account, looser_account) = if account_id == player_1 {
-            let total_spent =
-                if game.current_player_index == 0{
-                    game.total_time_spent[1]
-                }
-                else{
-                    env::block_timestamp() - game.last_turn_timestamp + game.total_time_spent[1]
-                };
-            log!("Player {} already spent: {} nanoseconds", player_2, total_spent);
-            assert!(total_spent > ONE_HOUR, "Too early to stop the game");
+                self.internal_stop_game(game_id);
 
-            (0, player_1, player_2)
-        } else if account_id == player_2 {
-            let total_spent =
-                if game.current_player_index == 1{
-                    game.total_time_spent[0]
-                }
-                else{
-                    env::block_timestamp() - game.last_turn_timestamp + game.total_time_spent[0]
-                };
-            log!("Player {} already spent: {} nanoseconds", player_1, total_spent);
-            assert!(total_spent > ONE_HOUR, "Too early to stop the game");
+                log!("\nGame over! Draw");
+            }
+        }
+    }
 
-            (1, player_2, player_1)
-        } else { panic!("No access") };
-        let token_balance = &game.reward;
-        let token_id = &token_balance.token_id;
-        self.internal_update_stats(&token_id, &looser_account,UpdateStatsAction::AddPenaltyGame, None, None);
+    /// Ends the game when the opponent ran out of time for their move
+    pub fn stop_game(&mut self, game_id: GameId) {
+        let game_to_save: GameToSave = self.internal_get_game(&game_id);
+        assert!(!game_to_save.is_finished(), "Game already finished");
+        let player_index = game_to_save.player_index(&env::predecessor_account_id());
+
+        let mut game: Game = game_to_save.into();
+        let opponent_index = 1 - player_index;
+        log!("Player {} has {} nanoseconds left", game.players[opponent_index].account_id,
+             game.remaining_time(opponent_index));
+        assert!(game.current_player_index == opponent_index && game.is_out_of_time(), "Too early to stop the game");
+
+        self.internal_finish_on_time(game_id, &mut game);
+        self.internal_save_played_game(&game_id, game);
+    }
 
-        self.internal_distribute_reward(token_balance, &winner_account);
+    /// Spends `days` of the vacation the caller banked in a correspondence
+    /// game, pushing the deadline of the move they are to make back by as many days
+    pub fn take_vacation(&mut self, game_id: GameId, days: u64) {
+        let mut game: Game = self.internal_get_game(&game_id).into();
+        assert!(!game.is_finished(), "Game already finished");
+        assert_eq!(game.current_player_account_id(), env::predecessor_account_id(), "No access");
+        assert!(!game.is_out_of_time(), "The move is already late");
+
+        game.take_vacation(days);
+        log!("{} takes {} vacation days, the move is due at {}", env::predecessor_account_id(), days,
+             game.move_deadline());
+        self.internal_save_played_game(&game_id, game);
+    }
+
+    // The player to move ran out of time: the opponent wins, and the player gets a penalty
+    fn internal_finish_on_time(&mut self, game_id: GameId, game: &mut Game) {
+        game.charge_clock(false);
+        let winner_index = 1 - game.current_player_index;
+        let winner_account = game.players[winner_index].account_id.clone();
+        let looser_account = game.current_player_account_id();
+        self.internal_update_stats(&game.reward.token_id, &looser_account, UpdateStatsAction::AddPenaltyGame, None, None);
+
+        self.internal_distribute_reward(&game.reward, &winner_account);
         game.winner_index = Some(winner_index);
-        self.games.insert(&game_id, &game);
 
         self.internal_stop_game(game_id);
+
+        log!("\nGame over! {} lost on time", looser_account);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use near_sdk::{MockedBlockchain, testing_env};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+
+    use super::*;
+    use crate::game::PlayerInfo;
+
+    fn set_context(predecessor: usize, timestamp: Timestamp) {
+        set_context_with_deposit(predecessor, timestamp, 0);
+    }
+
+    fn set_context_with_deposit(predecessor: usize, timestamp: Timestamp, deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(predecessor))
+            .block_timestamp(timestamp)
+            .attached_deposit(deposit)
+            .build());
+    }
+
+    fn config(vs_bot: bool) -> GameConfig {
+        GameConfig {
+            token_id: None,
+            deposit: None,
+            first_move: FirstMoveOptions::First,
+            opponent_id: None,
+            variant: GameVariant::American,
+            vs_bot,
+            disable_takebacks: false,
+            time_control: TimeControl::default(),
+        }
+    }
+
+    // a contract with a game of accounts(2), who moves first, against accounts(1)
+    fn start_game() -> Checkers {
+        set_context(0, 1_000);
+        let mut contract = Checkers::new();
+        for account in [1, 2] {
+            set_context_with_deposit(account, 1_000, MIN_DEPOSIT_NEAR);
+            contract.make_available(config(false), None);
+        }
+        contract.start_game(accounts(1).into(), None);
+        contract
+    }
+
+    const DAY: Timestamp = 24 * ONE_HOUR;
+
+    // a correspondence game of accounts(2), who moves first, against accounts(1),
+    // started at 1_000 with 3 days per move and 5 vacation days
+    fn start_correspondence_game() -> Checkers {
+        set_context(0, 1_000);
+        let mut contract = Checkers::new();
+        for account in [1, 2] {
+            set_context_with_deposit(account, 1_000, MIN_DEPOSIT_NEAR);
+            contract.make_available(GameConfig {
+                time_control: TimeControl::Correspondence(CorrespondenceControl { days_per_move: 3, vacation_days: 5 }),
+                ..config(false)
+            }, None);
+        }
+        contract.start_game(accounts(1).into(), None);
+        contract
+    }
+
+    // a contract whose house pool holds `pool`, with a game of accounts(1) against the bot
+    fn start_bot_game(pool: Balance, stake: Balance) -> Checkers {
+        set_context_with_deposit(0, 1_000, pool);
+        let mut contract = Checkers::new();
+        contract.fund_house_pool();
+
+        set_context_with_deposit(1, 1_000, stake);
+        contract.make_available(config(true), None);
+        contract
+    }
+
+    fn stats(contract: &Checkers, account: usize) -> near_sdk::serde_json::Value {
+        near_sdk::serde_json::to_value(contract.get_stats(accounts(account).into(), None)).unwrap()
+    }
+
+    #[test]
+    fn house_pool_matches_the_stake() {
+        let contract = start_bot_game(10 * MIN_DEPOSIT_NEAR, 2 * MIN_DEPOSIT_NEAR);
+        assert_eq!(8 * MIN_DEPOSIT_NEAR, contract.get_house_pool().0);
+        let game = contract.internal_get_game(&0);
+        assert_eq!(4 * MIN_DEPOSIT_NEAR, game.reward.balance);
+        assert_eq!(accounts(0).to_string(), game.player_2.account_id);
+        assert_eq!(1, stats(&contract, 0)["games_num"]);
+        assert_eq!(1, stats(&contract, 1)["games_num"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The house pool can't match the deposit")]
+    fn stake_over_the_house_pool() {
+        start_bot_game(MIN_DEPOSIT_NEAR, 2 * MIN_DEPOSIT_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "The stake is over the maximum against the bot")]
+    fn stake_over_the_maximum() {
+        start_bot_game(100 * DEFAULT_MAX_BOT_STAKE, 2 * DEFAULT_MAX_BOT_STAKE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attach at least 150 Tgas to play against the bot")]
+    fn move_against_the_bot_with_too_little_gas() {
+        let mut contract = start_bot_game(10 * MIN_DEPOSIT_NEAR, MIN_DEPOSIT_NEAR);
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(2_000)
+            .prepaid_gas(100_000_000_000_000)
+            .build());
+        contract.make_move(0, "c3 d4".into());
+    }
+
+    #[test]
+    fn owner_sets_the_maximum_stake() {
+        let mut contract = start_bot_game(100 * DEFAULT_MAX_BOT_STAKE, MIN_DEPOSIT_NEAR);
+        set_context(0, 2_000);
+        contract.set_max_bot_stake(U128(2 * DEFAULT_MAX_BOT_STAKE));
+        assert_eq!(2 * DEFAULT_MAX_BOT_STAKE, contract.get_max_bot_stake().0);
+
+        set_context_with_deposit(2, 2_000, 2 * DEFAULT_MAX_BOT_STAKE);
+        contract.make_available(config(true), None);
+        assert_eq!(4 * DEFAULT_MAX_BOT_STAKE, contract.internal_get_game(&1).reward.balance);
+    }
+
+    #[test]
+    fn bot_winnings_go_back_to_the_pool() {
+        let mut contract = start_bot_game(10 * MIN_DEPOSIT_NEAR, MIN_DEPOSIT_NEAR);
+        set_context_with_deposit(1, 2_000, ONE_YOCTO);
+        contract.give_up(0);
+
+        // both stakes less the fee of a tenth
+        assert_eq!(9 * MIN_DEPOSIT_NEAR + 18 * MIN_DEPOSIT_NEAR / 10, contract.get_house_pool().0);
+        assert_eq!(2 * MIN_DEPOSIT_NEAR / 10, contract.service_fee);
+
+        let (bot_stats, player_stats) = (stats(&contract, 0), stats(&contract, 1));
+        assert_eq!(1, bot_stats["victories_num"]);
+        assert_eq!((18 * MIN_DEPOSIT_NEAR / 10).to_string(), bot_stats["total_reward"]);
+        assert_eq!(1, player_stats["games_num"]);
+        assert_eq!(0, player_stats["victories_num"]);
+        assert_eq!("0", player_stats["total_reward"]);
+    }
+
+    #[test]
+    fn draw_against_the_bot_splits_the_stakes() {
+        let mut contract = start_bot_game(10 * MIN_DEPOSIT_NEAR, MIN_DEPOSIT_NEAR);
+
+        // a king each, one move before the draw without progress
+        let reward = contract.internal_get_game(&0).reward;
+        let position = parse_fen("B:WK32:BK1", GameVariant::American).ok().unwrap();
+        let mut game = Game::from_position(position, accounts(1).into(), accounts(0).into(), reward, GameVariant::American);
+        game.takebacks_allowed = false;
+        game.moves_without_progress = DRAW_MOVES_WITHOUT_PROGRESS - 1;
+        contract.internal_save_game(&0, game.into());
+
+        set_context(1, 2_000);
+        contract.make_move(0, "g1 h2".into());
+        assert!(contract.internal_get_game(&0).is_draw);
+
+        // each side gets its stake back less half the fee
+        assert_eq!(9 * MIN_DEPOSIT_NEAR + 9 * MIN_DEPOSIT_NEAR / 10, contract.get_house_pool().0);
+        assert_eq!(2 * MIN_DEPOSIT_NEAR / 10, contract.service_fee);
+        for account in [0, 1] {
+            let stats = stats(&contract, account);
+            assert_eq!(1, stats["games_num"]);
+            assert_eq!(1, stats["draws_num"]);
+            assert_eq!(0, stats["victories_num"]);
+        }
+    }
+
+    #[test]
+    fn games_saved_before_the_migration_are_read() {
+        set_context(0, 1_000);
+        let (player_1, player_2) = (Player { id: 1 }, Player { id: 2 });
+        let board = BoardToSave::new_checkerboard(CHECKERBOARD_SIZE, 3, &player_1, &player_2);
+        let player_1 = PlayerInfo { player: player_1, direction: Direction::IncreasingRank, account_id: accounts(1).into() };
+        let player_2 = PlayerInfo { player: player_2, direction: Direction::DecreasingRank, account_id: accounts(2).into() };
+        let reward = TokenBalance { token_id: Some("NEAR".into()), balance: 2 * MIN_DEPOSIT_NEAR };
+
+        // the fields of a game in the layout the contract was first deployed with
+        let game = (player_1, player_2, reward, None::<usize>, 0u64, 1_000 as Timestamp,
+                    vec![0 as Timestamp, 0], board, 0usize);
+        let games_prefix = StorageKey::Games.try_to_vec().unwrap();
+        env::storage_write(&[games_prefix, (0 as GameId).try_to_vec().unwrap()].concat(), &game.try_to_vec().unwrap());
+        env::state_write(&(
+            LookupMap::<GameId, GameToSaveV1>::new(StorageKey::Games),
+            UnorderedMap::<AccountId, VGameConfig>::new(StorageKey::AvailablePlayers),
+            UnorderedMap::<AccountId, VStats>::new(StorageKey::Stats),
+            UnorderedMap::<GameId, (AccountId, AccountId)>::new(StorageKey::AvailableGames),
+            UnorderedMap::<AccountId, WhitelistedToken>::new(StorageKey::WhitelistedTokens),
+            1 as GameId,
+            0 as Balance,
+        ));
+
+        let mut contract = Checkers::migrate();
+        let game = contract.internal_get_game(&0);
+        assert_eq!(accounts(1).to_string(), game.player_1.account_id);
+        assert_eq!(GameVariant::American, game.variant);
+        assert!(!game.is_draw);
+        assert_eq!(vec![ONE_HOUR, ONE_HOUR], game.remaining_time);
+        assert_eq!(accounts(1).to_string(), contract.get_active_player(0));
+        assert_eq!("B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12", contract.get_game_fen(0));
+
+        // the game goes on and moves to the versioned storage
+        set_context(1, 2_000);
+        contract.make_move(0, "c3 d4".into());
+        assert!(contract.legacy_games.get(&0).is_none());
+        assert_eq!(accounts(2).to_string(), contract.get_active_player(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "No suggestions in games against the bot")]
+    fn no_suggestions_against_the_bot() {
+        let contract = start_bot_game(10 * MIN_DEPOSIT_NEAR, MIN_DEPOSIT_NEAR);
+        contract.suggest_move(0, 4, None);
+    }
+
+    #[test]
+    fn moves_are_stored_apart_from_the_game() {
+        let mut contract = start_game();
+        set_context(2, 2_000);
+        contract.make_move(0, "c3 d4".into());
+        set_context(1, 3_000);
+        contract.make_move(0, "f6 e5".into());
+        assert_eq!(2, contract.internal_get_game(&0).moves_count);
+        assert_eq!(2, contract.get_game_moves(0, 0, 10).len());
+        let moves = contract.get_game_moves(0, 1, 10);
+        assert_eq!(vec![(5, 5), (4, 4)], moves[0].positions);
+
+        // the move taken back is dropped from the storage
+        contract.request_takeback(0);
+        set_context(2, 4_000);
+        contract.accept_takeback(0);
+        assert_eq!(1, contract.internal_get_game(&0).moves_count);
+        assert_eq!(1, contract.get_game_moves(0, 0, 10).len());
+
+        set_context(1, 5_000);
+        contract.make_move(0, "b6 a5".into());
+        let moves = contract.get_game_moves(0, 0, 10);
+        assert_eq!(2, moves.len());
+        assert_eq!(vec![(5, 1), (4, 0)], moves[1].positions);
+        assert!(contract.get_game_pdn(0).contains("1. 11-15 24-20"));
+    }
+
+    #[test]
+    fn vacation_pushes_the_deadline_back() {
+        let mut contract = start_correspondence_game();
+        set_context(2, 1_000 + DAY);
+        contract.take_vacation(0, 2);
+
+        let game = near_sdk::serde_json::to_value(contract.get_game(0)).unwrap();
+        assert_eq!(near_sdk::serde_json::json!([3, 5]), game["vacation_days"]);
+        assert_eq!(1_000 + 5 * DAY, game["move_deadline"]);
+
+        // the claim succeeds once the vacation is over
+        set_context(1, 1_000 + 5 * DAY + 1);
+        contract.stop_game(0);
+        assert!(contract.internal_get_game(&0).is_finished());
+    }
+
+    #[test]
+    #[should_panic(expected = "Too early to stop the game")]
+    fn no_claim_during_vacation() {
+        let mut contract = start_correspondence_game();
+        set_context(2, 1_000 + DAY);
+        contract.take_vacation(0, 2);
+
+        // past the deadline the move had before the vacation
+        set_context(1, 1_000 + 4 * DAY);
+        contract.stop_game(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "No access")]
+    fn no_vacation_on_the_opponent_move() {
+        let mut contract = start_correspondence_game();
+        set_context(1, 1_000 + DAY);
+        contract.take_vacation(0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough vacation days left. Asked: 6, Left: 5")]
+    fn no_vacation_beyond_the_bank() {
+        let mut contract = start_correspondence_game();
+        set_context(2, 1_000 + DAY);
+        contract.take_vacation(0, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "The move is already late")]
+    fn no_vacation_for_a_late_move() {
+        let mut contract = start_correspondence_game();
+        set_context(2, 1_000 + 3 * DAY + 1);
+        contract.take_vacation(0, 1);
     }
 }
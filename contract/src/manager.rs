@@ -22,16 +22,46 @@ pub struct GameConfig {
     pub(crate) deposit: Option<Balance>,
     pub(crate) first_move: FirstMoveOptions,
     pub(crate) opponent_id: Option<AccountId>,
+    #[serde(default)]
+    pub(crate) variant: GameVariant,
+    // play against the contract's engine, for stakes from the house pool
+    #[serde(default)]
+    pub(crate) vs_bot: bool,
+    // ranked games refuse takebacks, if either player asks so
+    #[serde(default)]
+    pub(crate) disable_takebacks: bool,
+    // both players must choose the same one
+    #[serde(default)]
+    pub(crate) time_control: TimeControl,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct GameConfigV1 {
+    token_id: Option<AccountId>,
+    deposit: Option<Balance>,
+    first_move: FirstMoveOptions,
+    opponent_id: Option<AccountId>,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
 pub enum VGameConfig {
+    V1(GameConfigV1),
     Current(GameConfig)
 }
 
 impl From<VGameConfig> for GameConfig {
     fn from(v_game_config: VGameConfig) -> Self {
         match v_game_config {
+            VGameConfig::V1(game_config) => GameConfig {
+                token_id: game_config.token_id,
+                deposit: game_config.deposit,
+                first_move: game_config.first_move,
+                opponent_id: game_config.opponent_id,
+                variant: GameVariant::default(),
+                vs_bot: false,
+                disable_takebacks: false,
+                time_control: TimeControl::default(),
+            },
             VGameConfig::Current(game_config) => game_config,
         }
     }
@@ -44,6 +74,10 @@ pub struct GameConfigOutput {
     deposit: U128,
     first_move: FirstMoveOptions,
     opponent_id: Option<AccountId>,
+    variant: GameVariant,
+    vs_bot: bool,
+    disable_takebacks: bool,
+    time_control: TimeControl,
 }
 
 impl From<GameConfig> for GameConfigOutput {
@@ -53,12 +87,28 @@ impl From<GameConfig> for GameConfigOutput {
             deposit: U128::from(config.deposit.unwrap_or(0)),
             first_move: config.first_move,
             opponent_id: config.opponent_id,
+            variant: config.variant,
+            vs_bot: config.vs_bot,
+            disable_takebacks: config.disable_takebacks,
+            time_control: config.time_control,
         }
     }
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct Stats {
+    referrer_id: Option<AccountId>,
+    affiliates: UnorderedSet<AffiliateId>,
+    games_num: u64,
+    victories_num: u64,
+    penalties_num: u64,
+    draws_num: u64,
+    total_reward: UnorderedMap<Option<TokenId>, Balance>,
+    total_affiliate_reward: UnorderedMap<Option<AffiliateId>, Balance>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StatsV1 {
     referrer_id: Option<AccountId>,
     affiliates: UnorderedSet<AffiliateId>,
     games_num: u64,
@@ -76,6 +126,7 @@ impl Stats {
             games_num: 0,
             victories_num: 0,
             penalties_num: 0,
+            draws_num: 0,
             total_reward: UnorderedMap::new(StorageKey::TotalRewards { account_id: account_id.clone() }),
             total_affiliate_reward: UnorderedMap::new(StorageKey::TotalAffiliateRewards { account_id: account_id.clone() }),
         }
@@ -84,12 +135,23 @@ impl Stats {
 
 #[derive(BorshSerialize, BorshDeserialize)]
 pub enum VStats {
+    V1(StatsV1),
     Current(Stats),
 }
 
 impl From<VStats> for Stats {
     fn from(v_stats: VStats) -> Self {
         match v_stats {
+            VStats::V1(stats) => Stats {
+                referrer_id: stats.referrer_id,
+                affiliates: stats.affiliates,
+                games_num: stats.games_num,
+                victories_num: stats.victories_num,
+                penalties_num: stats.penalties_num,
+                draws_num: 0,
+                total_reward: stats.total_reward,
+                total_affiliate_reward: stats.total_affiliate_reward,
+            },
             VStats::Current(stats) => stats,
         }
     }
@@ -103,6 +165,7 @@ pub struct StatsOutput {
     games_num: u64,
     victories_num: u64,
     penalties_num: u64,
+    draws_num: u64,
     token_id: TokenId,
     total_reward: U128,
     total_affiliate_reward: U128,
@@ -116,6 +179,7 @@ impl StatsOutput {
             games_num: stats.games_num,
             victories_num: stats.victories_num,
             penalties_num: stats.penalties_num,
+            draws_num: stats.draws_num,
             token_id: token_id.clone().unwrap_or_else(|| "NEAR".into()),
             total_reward: U128::from(stats.total_reward.get(&token_id).unwrap_or(0)),
             total_affiliate_reward: U128::from(stats.total_affiliate_reward.get(&token_id).unwrap_or(0)),
@@ -123,7 +187,7 @@ impl StatsOutput {
     }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TokenBalance {
     pub(crate) token_id: Option<AccountId>,
@@ -146,7 +210,7 @@ impl From<TokenBalance> for TokenBalanceOutput {
     }
 }
 
-pub type BoardOutput = [Vec<i8>; 8];
+pub type BoardOutput = Vec<Vec<i8>>;
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -156,12 +220,62 @@ pub struct GameOutput {
     current_player_index: usize,
     reward: TokenBalanceOutput,
     winner_index: Option<usize>,
+    is_draw: bool,
+    draw_offer_index: Option<usize>,
+    takebacks_allowed: bool,
+    takeback_request_index: Option<usize>,
     turns: u64,
     last_turn_timestamp: Timestamp,
     total_time_spent: Vec<Timestamp>,
+    time_control: TimeControl,
+    // nanoseconds left on the clock of each player, the clock of the player to move still running.
+    // In correspondence games, the vacation left in the bank
+    remaining_time: Vec<Timestamp>,
+    // in correspondence games, the vacation days each player has left in the bank
+    vacation_days: Option<Vec<u64>>,
+    // when the player to move loses on time, if the game isn't over, vacation taken included
+    move_deadline: Option<Timestamp>,
+    variant: GameVariant,
     board: BoardOutput,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MoveErrorKind {
+    ParseError,
+    OutOfBounds,
+    NotYourPiece,
+    MustJump,
+    IncompleteJump,
+    NotPriorityCapture,
+    IllegalMove,
+    GameOver,
+}
+
+/// Whether a move would be accepted by `make_move`, and if not why and
+/// which moves the player to move may make instead
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MoveValidation {
+    pub is_valid: bool,
+    pub error: Option<MoveErrorKind>,
+    pub message: Option<String>,
+    pub simple_moves: Vec<SimpleMove>,
+    pub jump_moves: Vec<JumpMove>,
+}
+
+/// The move the engine would play, as a simple move or a single jump
+/// sequence, with its score for the player to move and the static
+/// evaluation of the current position
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MoveSuggestion {
+    pub simple_move: Option<SimpleMove>,
+    pub jump_move: Option<JumpMove>,
+    pub score: i32,
+    pub depth: u32,
+    pub evaluation: Evaluation,
+}
 
 #[derive(PartialEq)]
 pub enum UpdateStatsAction {
@@ -172,25 +286,26 @@ pub enum UpdateStatsAction {
     AddTotalReward,
     AddAffiliateReward,
     AddPenaltyGame,
+    AddDrawGame,
 }
 
 impl Checkers {
 
-    pub(crate) fn internal_distribute_reward(&mut self, token_balance: &TokenBalance, winner_id: &AccountId) {
-       
-        let amount = token_balance.balance;
-        let token_id = token_balance.token_id.clone();
-        let fee = amount / 10;
+    pub(crate) fn internal_transfer(&mut self, token_id: &Option<TokenId>, account_id: &AccountId, amount: Balance) {
+        // the bot's winnings and refunds go back to the house pool
+        if *account_id == env::current_account_id() {
+            self.house_pool += amount;
+            return;
+        }
 
-        let winner_reward: Balance = amount - fee;
-        if token_id == Some("NEAR".into()) {
-            Promise::new(winner_id.clone()).transfer(winner_reward);
+        if token_id == &Some("NEAR".into()) {
+            Promise::new(account_id.clone()).transfer(amount);
         } else {
             match token_id {
                 Some(ref token_contract) => {
                     ext_ft::ft_transfer(
-                        winner_id.clone(),
-                        winner_reward.to_string(),
+                        account_id.clone(),
+                        amount.to_string(),
                         &token_contract,
                         ONE_YOCTO,
                         CALLBACK_GAS 
@@ -201,38 +316,36 @@ impl Checkers {
                 }
             }
         }
+    }
 
-        log!("Winner is {}. Reward: {}", winner_id, winner_reward);
-
-        // Referrer rewards
-        let stats = self.internal_get_stats(winner_id);
-        let referrer_fee = if let Some(referrer_id) = stats.referrer_id {
+    // Pays half of the fee taken from the account to its referrer, if any.
+    // Returns the amount paid.
+    fn internal_pay_referrer_fee(&mut self, token_id: &Option<TokenId>, account_id: &AccountId, fee: Balance) -> Balance {
+        let stats = self.internal_get_stats(account_id);
+        if let Some(referrer_id) = stats.referrer_id {
             let referrer_fee = fee / 2;
             log!("Affiliate reward for {} is {}", referrer_id, referrer_fee);
-            self.internal_update_stats(&token_id, &referrer_id, UpdateStatsAction::AddAffiliateReward, None, Some(referrer_fee));
-
-            if token_id == Some("NEAR".into()) {
-                Promise::new(referrer_id.clone()).transfer(referrer_fee);
-            } else {
-                match token_id {
-                    Some(ref token_contract) => {
-                        ext_ft::ft_transfer(
-                            referrer_id.clone(),
-                            referrer_fee.to_string(),
-                            &token_contract,
-                            ONE_YOCTO,
-                            CALLBACK_GAS 
-                        );
-                    }
-                    _ => {
-                        panic!("no token_id find in contract") 
-                    }
-                }
-            }
+            self.internal_update_stats(token_id, &referrer_id, UpdateStatsAction::AddAffiliateReward, None, Some(referrer_fee));
+            self.internal_transfer(token_id, &referrer_id, referrer_fee);
             referrer_fee
         } else {
             0
-        };
+        }
+    }
+
+    pub(crate) fn internal_distribute_reward(&mut self, token_balance: &TokenBalance, winner_id: &AccountId) {
+       
+        let amount = token_balance.balance;
+        let token_id = token_balance.token_id.clone();
+        let fee = amount / 10;
+
+        let winner_reward: Balance = amount - fee;
+        self.internal_transfer(&token_id, winner_id, winner_reward);
+
+        log!("Winner is {}. Reward: {}", winner_id, winner_reward);
+
+        // Referrer rewards
+        let referrer_fee = self.internal_pay_referrer_fee(&token_id, winner_id, fee);
 
         self.service_fee += fee - referrer_fee;
 
@@ -243,6 +356,29 @@ impl Checkers {
         // TODO add to stats
     }
 
+    /// Returns the stakes of a drawn game to both players. The fee is taken
+    /// as for a won game and split between the players' shares.
+    pub(crate) fn internal_distribute_draw(&mut self, token_balance: &TokenBalance, players: &[AccountId; 2]) {
+        let amount = token_balance.balance;
+        let token_id = token_balance.token_id.clone();
+        let fee = amount / 10;
+
+        let player_fee = fee / 2;
+        let player_refund: Balance = (amount - fee) / 2;
+
+        let mut referrer_fees = 0;
+        for player_id in players {
+            self.internal_transfer(&token_id, player_id, player_refund);
+            log!("Draw. {} gets back: {}", player_id, player_refund);
+
+            referrer_fees += self.internal_pay_referrer_fee(&token_id, player_id, player_fee);
+
+            self.internal_update_stats(&token_id, player_id, UpdateStatsAction::AddDrawGame, None, None);
+        }
+
+        self.service_fee += amount - 2 * player_refund - referrer_fees;
+    }
+
     pub(crate) fn internal_update_stats(&mut self,
                                         token_id: &Option<String>,
                                         account_id: &AccountId,
@@ -294,13 +430,49 @@ impl Checkers {
             }
         } else if action == UpdateStatsAction::AddPenaltyGame {
             stats.penalties_num += 1;
+        } else if action == UpdateStatsAction::AddDrawGame {
+            stats.draws_num += 1;
         }
 
         self.stats.insert(account_id, &VStats::Current(stats));
     }
 
     pub(crate) fn internal_get_game(&self, game_id: &GameId) -> GameToSave {
-        self.games.get(game_id).expect("Game not found")
+        match self.games.get(game_id) {
+            Some(game) => game.into(),
+            None => self.legacy_games.get(game_id).expect("Game not found").into(),
+        }
+    }
+
+    // Games saved before the migration move to the versioned storage when saved again
+    pub(crate) fn internal_save_game(&mut self, game_id: &GameId, game: GameToSave) {
+        self.legacy_games.remove(game_id);
+        self.games.insert(game_id, &VGameToSave::Current(game));
+    }
+
+    // Saves a game together with the moves made since it was loaded, after
+    // dropping the stored moves that were taken back
+    pub(crate) fn internal_save_played_game(&mut self, game_id: &GameId, game: Game) {
+        let mut moves = self.game_moves.get(game_id)
+            .unwrap_or_else(|| Vector::new(StorageKey::MovesOfGame { game_id: *game_id }));
+        let moves_kept = game.moves_count - game.moves.len() as u64;
+        while moves.len() > moves_kept {
+            moves.pop();
+        }
+        moves.extend(game.moves.iter().cloned());
+        self.game_moves.insert(game_id, &moves);
+
+        self.internal_save_game(game_id, game.into());
+    }
+
+    // The moves of a game, loaded for the views that show them
+    pub(crate) fn internal_get_game_moves(&self, game_id: &GameId, from_index: u64, limit: u64) -> Vec<MoveRecord> {
+        match self.game_moves.get(game_id) {
+            Some(moves) => (from_index..std::cmp::min(from_index.saturating_add(limit), moves.len()))
+                .map(|index| moves.get(index).unwrap())
+                .collect(),
+            None => Vec::new(),
+        }
     }
 
     pub(crate) fn is_account_exists(&self, account_id: &Option<AccountId>) -> bool {
@@ -353,7 +525,7 @@ impl Checkers {
                 match token_id {
                     
                     Some(ref token_contract) => {
-                        assert_eq!(env::attached_deposit(), ONE_YOCTO, "Attach 1 yocto");
+                        assert_eq!(util::attached_deposit(), ONE_YOCTO, "Attach 1 yocto");
                         PromiseOrValue::Promise(ext_ft::ft_transfer(
                             account_id.clone(),
                             config.deposit.unwrap_or(0).to_string(),
@@ -382,6 +554,14 @@ impl Checkers {
 
     pub fn get_game(&self, game_id: GameId) -> GameOutput {
         let game: Game = self.internal_get_game(&game_id).into();
+        let (remaining_time, move_deadline) = if game.is_finished() {
+            (game.remaining_time.clone(), None)
+        } else {
+            (vec![game.remaining_time(0), game.remaining_time(1)], Some(game.move_deadline()))
+        };
+        let vacation_days = remaining_time.iter()
+            .map(|remaining| game.time_control.vacation_days(*remaining))
+            .collect();
 
         GameOutput {
             player_1: game.players[0].account_id.clone(),
@@ -389,18 +569,64 @@ impl Checkers {
             current_player_index: game.current_player_index,
             reward: game.reward.into(),
             winner_index: game.winner_index,
+            is_draw: game.is_draw,
+            draw_offer_index: game.draw_offer_index,
+            takebacks_allowed: game.takebacks_allowed,
+            takeback_request_index: game.takeback_request_index,
             turns: game.turns,
             last_turn_timestamp: game.last_turn_timestamp,
             total_time_spent: game.total_time_spent,
+            time_control: game.time_control,
+            remaining_time,
+            vacation_days,
+            move_deadline,
+            variant: game.variant,
             board: game.board.into(),
         }
     }
 
+    /// Returns the moves of a game in the order they were made,
+    /// up to `limit` of them starting with the move at `from_index`.
+    pub fn get_game_moves(&self, game_id: GameId, from_index: u64, limit: u64) -> Vec<MoveRecord> {
+        self.internal_get_game_moves(&game_id, from_index, limit)
+    }
+
+    /// Returns the game in Portable Draughts Notation
+    pub fn get_game_pdn(&self, game_id: GameId) -> String {
+        let mut game: Game = self.internal_get_game(&game_id).into();
+        game.moves = self.internal_get_game_moves(&game_id, 0, game.moves_count);
+        pdn::game_to_pdn(&game)
+    }
+
+    /// Returns the current position as a FEN string, `W:W21,22,K30:B1,2,K9`
+    pub fn get_game_fen(&self, game_id: GameId) -> String {
+        let game: Game = self.internal_get_game(&game_id).into();
+        fen::game_to_fen(&game)
+    }
+
+    /// Checks a move of the player to move without playing it
+    pub fn validate_move(&self, game_id: GameId, line: String) -> MoveValidation {
+        let mut game: Game = self.internal_get_game(&game_id).into();
+        util::validate_move(&mut game, &line)
+    }
+
     pub fn get_available_moves(&self, game_id: GameId) -> (Vec<SimpleMove>, Vec<JumpMove>) {
         let game: Game = self.internal_get_game(&game_id).into();
         (game.available_simple_moves, game.available_jump_moves)
     }
 
+    /// Searches the best move of the player to move, `depth` plies deep at most.
+    /// The evaluation weights default to the ones of the bot. Not available
+    /// in games against the bot.
+    pub fn suggest_move(&self, game_id: GameId, depth: u32, weights: Option<EvalWeights>) -> MoveSuggestion {
+        let game: Game = self.internal_get_game(&game_id).into();
+        assert!(!game.is_finished(), "Game is over");
+        // the engine must not play against the house pool for the player
+        assert!(game.players.iter().all(|player| player.account_id != env::current_account_id()),
+                "No suggestions in games against the bot");
+        bot::suggest_move(&game, depth, weights.unwrap_or_default())
+    }
+
     pub fn get_available_players(&self, from_index: u64, limit: u64) -> Vec<(AccountId, GameConfigOutput)> {
         let keys = self.available_players.keys_as_vector();
         let values = self.available_players.values_as_vector();
@@ -0,0 +1,492 @@
+use near_sdk::Timestamp;
+
+use crate::*;
+
+// Longest line of the move text, as desktop tools expect
+const PDN_LINE_LENGTH: usize = 79;
+
+const NANOSECONDS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// Writes a game in Portable Draughts Notation: the tag pairs followed by
+/// the numbered moves in square notation, `-` separating the squares of a
+/// simple move and `x` the squares of a capture.
+pub fn game_to_pdn(game: &Game) -> String {
+    let (white_index, black_index) = if first_player_is_black(game.variant) { (1, 0) } else { (0, 1) };
+    let date = game.moves.first()
+        .map(|the_move| the_move.timestamp)
+        .unwrap_or(game.last_turn_timestamp);
+
+    let mut pdn = String::new();
+    pdn.push_str(&format!("[Event \"{:?} checkers\"]\n", game.variant));
+    pdn.push_str(&format!("[Date \"{}\"]\n", format_date(date)));
+    pdn.push_str(&format!("[White \"{}\"]\n", game.players[white_index].account_id));
+    pdn.push_str(&format!("[Black \"{}\"]\n", game.players[black_index].account_id));
+    pdn.push_str(&format!("[Result \"{}\"]\n", result(game, white_index)));
+    pdn.push_str(&format!("[GameType \"{}\"]\n", game_type(game.variant)));
+    pdn.push('\n');
+
+    // a move number stays on the line of the move it numbers
+    let mut tokens = Vec::new();
+    for (index, the_move) in game.moves.iter().enumerate() {
+        let move_text = move_to_pdn(game.rules(), the_move);
+        if index % 2 == 0 {
+            tokens.push(format!("{}. {}", index / 2 + 1, move_text));
+        } else {
+            tokens.push(move_text);
+        }
+    }
+    tokens.push(result(game, white_index).to_string());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > PDN_LINE_LENGTH {
+            pdn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pdn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pdn.push_str(&token);
+    }
+    pdn.push('\n');
+
+    pdn
+}
+
+/// Writes a move in square notation, such as `11-15` or `22x15x6`
+pub fn move_to_pdn(rules: &dyn RuleSet, the_move: &MoveRecord) -> String {
+    let separator = if the_move.captures.is_empty() { "-" } else { "x" };
+    the_move.positions.iter()
+        .map(|&(row, col)| rules.square_number(row as usize, col as usize)
+            .expect("Move through a square without a number")
+            .to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Why a PDN game can't be replayed
+#[derive(Debug, PartialEq, Eq)]
+pub enum PdnError {
+    /// A tag pair or a token of the move text can't be read
+    InvalidToken(String),
+    /// The GameType tag names a variant that isn't played here
+    UnsupportedGameType(String),
+    /// The FEN tag isn't a valid position
+    InvalidPosition(FenError),
+    /// A move names a square that isn't on the board
+    InvalidSquare { move_number: usize, the_move: String },
+    /// A capture written without its intermediate squares matches several captures
+    AmbiguousCapture { move_number: usize, the_move: String },
+    /// The move isn't legal in the position it is played in
+    IllegalMove { move_number: usize, the_move: String, error: MoveError },
+    /// A move follows the end of the game
+    MoveAfterGameOver { move_number: usize, the_move: String },
+}
+
+// A move of the move text, with the squares it goes through
+struct PdnMove {
+    text: String,
+    squares: Vec<usize>,
+    capture: bool,
+}
+
+/// Reads a game in Portable Draughts Notation and replays its moves from
+/// the starting position, or from the position of the FEN tag. Returns the game after the last move, or the
+/// first move that can't be played. The Result tag isn't checked, as games
+/// often end by resignation, and repetitions or moves without progress don't
+/// end the replayed game.
+pub fn replay_pdn(pdn: &str) -> Result<Game, PdnError> {
+    let (tags, move_text) = read_tags(pdn)?;
+    let tag = |name: &str| tags.iter()
+        .find(|(tag_name, _)| tag_name == name)
+        .map(|(_, value)| value.clone());
+
+    let variant = variant_from_tags(tag("GameType"), tag("Event"))?;
+    let white = tag("White").unwrap_or_else(|| "?".to_string());
+    let black = tag("Black").unwrap_or_else(|| "?".to_string());
+    let (player_1, player_2) = if first_player_is_black(variant) { (black, white) } else { (white, black) };
+
+    let reward = TokenBalance { token_id: None, balance: 0 };
+
+    let mut game = match tag("FEN") {
+        Some(fen) => {
+            let position = parse_fen(&fen, variant).map_err(PdnError::InvalidPosition)?;
+            Game::from_position(position, player_1, player_2, reward, variant)
+        }
+        None => Game::new(player_1, player_2, reward, variant),
+    };
+
+    // a position set up with the second player to move starts with "1..."
+    let first_move_index = game.current_player_index;
+    for (index, the_move) in read_moves(&move_text)?.into_iter().enumerate() {
+        let move_number = (first_move_index + index) / 2 + 1;
+        if game.winner_index.is_some() || game.is_draw {
+            return Err(PdnError::MoveAfterGameOver { move_number, the_move: the_move.text });
+        }
+
+        match apply_pdn_move(&mut game, &the_move, move_number)? {
+            GameState::GameOver { winner_id } => game.winner_index = Some(winner_id),
+            // over the board draws by repetition or without progress are
+            // claimed, and recorded games often play on past them
+            GameState::Draw | GameState::InProgress => {}
+        }
+        game.turns += 1;
+    }
+
+    Ok(game)
+}
+
+fn apply_pdn_move(game: &mut Game, the_move: &PdnMove, move_number: usize) -> Result<GameState, PdnError> {
+    let rules = game.rules();
+    let positions = the_move.squares.iter()
+        .map(|&square| rules.square_position(square))
+        .collect::<Option<Vec<BoardPosition>>>()
+        .ok_or_else(|| PdnError::InvalidSquare { move_number, the_move: the_move.text.clone() })?;
+
+    let result = if the_move.capture {
+        let positions = complete_capture(game, positions)
+            .ok_or_else(|| PdnError::AmbiguousCapture { move_number, the_move: the_move.text.clone() })?;
+        game.apply_jump_move(positions)
+    } else if positions.len() == 2 {
+        game.apply_simple_move(SimpleMove::new(
+            positions[0].row, positions[0].column, positions[1].row, positions[1].column))
+    } else {
+        Err(MoveError::InvalidMove)
+    };
+
+    result.map_err(|error| PdnError::IllegalMove { move_number, the_move: the_move.text.clone(), error })
+}
+
+// Captures are often written with their first and last squares only. Finds the
+// available capture going through the written squares, or keeps them as they
+// are if there is none. Returns None if several captures match.
+fn complete_capture(game: &Game, positions: Vec<BoardPosition>) -> Option<Vec<BoardPosition>> {
+    let goes_through = |sequence: &Vec<BoardPosition>| {
+        let mut sequence_positions = sequence.iter();
+        sequence.first() == positions.first()
+            && sequence.last() == positions.last()
+            && positions.iter().all(|position| sequence_positions.any(|other| other == position))
+    };
+
+    let mut matches: Vec<Vec<BoardPosition>> = game.available_jump_moves.iter()
+        .flat_map(|jump_move| jump_move.sequences())
+        .filter(goes_through)
+        .collect();
+
+    match matches.len() {
+        0 => Some(positions),
+        1 => matches.pop(),
+        _ if matches.contains(&positions) => Some(positions),
+        _ => None,
+    }
+}
+
+// Splits the tag pairs, as (name, value), from the move text
+fn read_tags(pdn: &str) -> Result<(Vec<(String, String)>, String), PdnError> {
+    let mut tags = Vec::new();
+    let mut move_text = String::new();
+
+    for line in pdn.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let invalid_tag = || PdnError::InvalidToken(line.to_string());
+            let content = line.trim_start_matches('[').trim_end_matches(']');
+            let (name, value) = content.split_at(content.find(' ').ok_or_else(invalid_tag)?);
+            let value = value.trim();
+            if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+                return Err(invalid_tag());
+            }
+            tags.push((name.to_string(), value[1..value.len() - 1].to_string()));
+        } else {
+            move_text.push_str(line);
+            move_text.push('\n');
+        }
+    }
+
+    Ok((tags, move_text))
+}
+
+fn variant_from_tags(game_type: Option<String>, event: Option<String>) -> Result<GameVariant, PdnError> {
+    let game_type = match game_type {
+        None => return Ok(GameVariant::American),
+        Some(game_type) => game_type,
+    };
+
+    // the game type may be followed by the board description: "21,B,8,8,N1,0"
+    match game_type.split(',').next().unwrap_or("").trim() {
+        "20" => Ok(GameVariant::International),
+        "21" if event.map(|event| event.starts_with("Giveaway")).unwrap_or(false) =>
+            Ok(GameVariant::Giveaway),
+        "21" => Ok(GameVariant::American),
+        "22" => Ok(GameVariant::Italian),
+        "25" => Ok(GameVariant::Russian),
+        "30" => Ok(GameVariant::Turkish),
+        _ => Err(PdnError::UnsupportedGameType(game_type)),
+    }
+}
+
+// Reads the moves of the move text, skipping move numbers, comments,
+// variations and annotations. Reading stops at the game result.
+fn read_moves(move_text: &str) -> Result<Vec<PdnMove>, PdnError> {
+    // drop the comments and the variations, which may be nested
+    let mut main_line = String::new();
+    let mut in_comment = false;
+    let mut variation_depth = 0;
+    for character in move_text.chars() {
+        match character {
+            '{' if !in_comment => in_comment = true,
+            '}' if in_comment => in_comment = false,
+            '(' if !in_comment => variation_depth += 1,
+            ')' if !in_comment && variation_depth > 0 => variation_depth -= 1,
+            _ if in_comment || variation_depth > 0 => {}
+            _ => main_line.push(character),
+        }
+    }
+
+    let mut moves = Vec::new();
+    for token in main_line.split_whitespace() {
+        if ["1-0", "0-1", "1/2-1/2", "2-0", "0-2", "1-1", "*"].contains(&token) {
+            break;
+        }
+
+        // the move number may be glued to the move: "1.11-15"
+        let token = match token.rfind('.') {
+            Some(index) => &token[index + 1..],
+            None => token,
+        };
+        let token = token.trim_end_matches(['!', '?']);
+        if token.is_empty() {
+            continue;
+        }
+
+        let capture = token.contains('x');
+        let squares = token.split(['-', 'x'])
+            .map(|square| square.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| PdnError::InvalidToken(token.to_string()))?;
+        if squares.len() < 2 {
+            return Err(PdnError::InvalidToken(token.to_string()));
+        }
+
+        moves.push(PdnMove { text: token.to_string(), squares, capture });
+    }
+
+    Ok(moves)
+}
+
+// In American checkers Black moves first, in the other variants White does
+pub(crate) fn first_player_is_black(variant: GameVariant) -> bool {
+    matches!(variant, GameVariant::American | GameVariant::Giveaway)
+}
+
+// Game types of the PDN standard, giveaway games use the board of American checkers
+fn game_type(variant: GameVariant) -> u32 {
+    match variant {
+        GameVariant::International => 20,
+        GameVariant::American | GameVariant::Giveaway => 21,
+        GameVariant::Italian => 22,
+        GameVariant::Russian => 25,
+        GameVariant::Turkish => 30,
+    }
+}
+
+// The score of White comes first
+fn result(game: &Game, white_index: usize) -> &'static str {
+    match game.winner_index {
+        Some(winner_index) if winner_index == white_index => "1-0",
+        Some(_) => "0-1",
+        None if game.is_draw => "1/2-1/2",
+        None => "*",
+    }
+}
+
+// Formats a block timestamp as the PDN date YYYY.MM.DD, unknown when 0
+fn format_date(timestamp: Timestamp) -> String {
+    if timestamp == 0 {
+        return "????.??.??".to_string();
+    }
+
+    // civil date from the days since 1970-01-01, with years starting in March
+    let days = (timestamp / NANOSECONDS_PER_DAY) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn new_game(variant: GameVariant) -> Game {
+        Game::new("alice".into(),
+                  "bob".into(),
+                  TokenBalance{ token_id: Some("NEAR".into()), balance: 1 },
+                  variant)
+    }
+
+    #[test]
+    fn american_square_numbers() {
+        let rules = GameVariant::American.rules();
+        assert_eq!(Some(1), rules.square_number(0, 6));
+        assert_eq!(Some(12), rules.square_number(2, 0));
+        assert_eq!(Some(32), rules.square_number(7, 1));
+        assert_eq!(None, rules.square_number(0, 1));
+        assert_eq!(Some(BoardPosition::new(3, 1)), rules.square_position(16));
+    }
+
+    #[test]
+    fn international_square_numbers() {
+        let rules = GameVariant::International.rules();
+        assert_eq!(Some(1), rules.square_number(9, 1));
+        assert_eq!(Some(46), rules.square_number(0, 0));
+        assert_eq!(Some(50), rules.square_number(0, 8));
+        assert_eq!(Some(BoardPosition::new(6, 0)), rules.square_position(16));
+    }
+
+    #[test]
+    fn export_american_game() {
+        let mut game = new_game(GameVariant::American);
+        game.apply_simple_move(SimpleMove::new(2, 2, 3, 3)).unwrap();
+        game.apply_simple_move(SimpleMove::new(5, 5, 4, 4)).unwrap();
+        game.apply_jump_move(vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)]).unwrap();
+
+        assert_eq!("[Event \"American checkers\"]\n\
+                    [Date \"????.??.??\"]\n\
+                    [White \"bob\"]\n\
+                    [Black \"alice\"]\n\
+                    [Result \"*\"]\n\
+                    [GameType \"21\"]\n\
+                    \n\
+                    1. 11-15 22-18 2. 15x22 *\n",
+                   game_to_pdn(&game));
+    }
+
+    #[test]
+    fn export_result_and_long_move_list() {
+        let mut game = new_game(GameVariant::International);
+        for index in 0..32 {
+            let positions = if index % 2 == 0 { vec![(3, 1), (4, 0)] } else { vec![(6, 0), (5, 1)] };
+            game.moves.push(MoveRecord {
+                player_index: index % 2,
+                timestamp: 1_700_000_000_000_000_000,
+                positions,
+                captures: vec![],
+                promoted: false,
+            });
+        }
+        game.is_draw = true;
+
+        let pdn = game_to_pdn(&game);
+        assert!(pdn.starts_with("[Event \"International checkers\"]\n[Date \"2023.11.14\"]\n\
+                                 [White \"alice\"]\n[Black \"bob\"]\n[Result \"1/2-1/2\"]\n[GameType \"20\"]\n\n\
+                                 1. 31-26 16-21 2. 31-26 16-21 3. 31-26"));
+        assert!(pdn.ends_with("\n16. 31-26 16-21 1/2-1/2\n"));
+        assert!(pdn.lines().all(|line| line.len() <= PDN_LINE_LENGTH));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!("2023.11.14", format_date(1_700_000_000_000_000_000));
+        assert_eq!("1970.01.01", format_date(1));
+        assert_eq!("2024.02.29", format_date(1_709_164_800_000_000_000));
+    }
+
+    #[test]
+    fn replay_exported_game() {
+        let mut game = new_game(GameVariant::American);
+        game.apply_simple_move(SimpleMove::new(2, 2, 3, 3)).unwrap();
+        game.apply_simple_move(SimpleMove::new(5, 5, 4, 4)).unwrap();
+        game.apply_jump_move(vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)]).unwrap();
+        game.apply_jump_move(vec![BoardPosition::new(6, 4), BoardPosition::new(4, 6)]).unwrap();
+
+        let replayed_game = replay_pdn(&game_to_pdn(&game)).unwrap();
+        assert_eq!(game.board.position_key(), replayed_game.board.position_key());
+        assert_eq!(game.moves, replayed_game.moves);
+        assert_eq!("alice", replayed_game.players[0].account_id);
+        assert_eq!(4, replayed_game.turns);
+    }
+
+    #[test]
+    fn replay_reports_first_illegal_move() {
+        let pdn = "[Event \"American checkers\"]\n\
+                   [GameType \"21\"]\n\
+                   \n\
+                   1. 11-15 {a comment} 22-18 (22-17 15-19) 2. 10-14 15x22 *\n";
+
+        let result = replay_pdn(pdn);
+        assert_eq!(Err(PdnError::IllegalMove {
+            move_number: 2,
+            the_move: "10-14".to_string(),
+            error: MoveError::ShouldHaveJumped,
+        }), result.map(|game| game.turns));
+    }
+
+    #[test]
+    fn replay_capture_without_intermediate_squares() {
+        let pdn = "[GameType \"20\"]\n\
+                   1. 32-28 19-23 2. 28x19 14x23 3. 37-32 13-19 4. 32-28 23x32 1-0";
+
+        let game = replay_pdn(pdn).unwrap();
+        assert_eq!(8, game.moves.len());
+        assert_eq!(GameVariant::International, game.variant);
+
+        let result = replay_pdn("1. 11-15 22-18 2. 15x29 *");
+        assert_eq!(Err(PdnError::IllegalMove {
+            move_number: 2,
+            the_move: "15x29".to_string(),
+            error: MoveError::InvalidMove,
+        }), result.map(|game| game.turns));
+    }
+
+    #[test]
+    fn replay_from_fen_position() {
+        let pdn = "[GameType \"21\"]\n\
+                   [FEN \"W:W18,K27:B14\"]\n\
+                   1... 18x9 1-0";
+
+        let game = replay_pdn(pdn).unwrap();
+        assert_eq!("B:W9,K27:B", fen::game_to_fen(&game));
+        assert_eq!(Some(1), game.winner_index);
+        assert_eq!(Err(PdnError::MoveAfterGameOver { move_number: 2, the_move: "27-23".to_string() }),
+                   replay_pdn("[FEN \"W:W18,K27:B14\"]\n1... 18x9 2. 27-23 *").map(|game| game.turns));
+
+        let result = replay_pdn("[FEN \"W:W18:B14\"]\n1... 18-15 *");
+        assert_eq!(Err(PdnError::IllegalMove {
+            move_number: 1,
+            the_move: "18-15".to_string(),
+            error: MoveError::ShouldHaveJumped,
+        }), result.map(|game| game.turns));
+        assert_eq!(Err(PdnError::InvalidPosition(FenError::DuplicateSquare(14))),
+                   replay_pdn("[FEN \"W:W14:B14\"]\n*").map(|game| game.turns));
+    }
+
+    #[test]
+    fn replay_plays_on_after_repetitions() {
+        let pdn = "[GameType \"21\"]\n\
+                   [FEN \"W:WK32:BK1\"]\n\
+                   1... 32-28 2. 1-5 28-32 3. 5-1 32-28 4. 1-5 28-32 5. 5-1 32-28 6. 1-5 28-32 7. 5-1 *";
+
+        let game = replay_pdn(pdn).unwrap();
+        assert!(!game.is_draw);
+        assert_eq!(12, game.turns);
+        assert!(game.is_drawn_position());
+    }
+
+    #[test]
+    fn replay_rejects_unknown_variants_and_squares() {
+        assert_eq!(Err(PdnError::UnsupportedGameType("26".to_string())),
+                   replay_pdn("[GameType \"26\"]\n1. 21-17 *").map(|game| game.turns));
+        assert_eq!(Err(PdnError::InvalidSquare { move_number: 1, the_move: "33-29".to_string() }),
+                   replay_pdn("1. 33-29 *").map(|game| game.turns));
+        assert_eq!(Err(PdnError::InvalidToken("e3-f4".to_string())),
+                   replay_pdn("1. e3-f4 *").map(|game| game.turns));
+    }
+}
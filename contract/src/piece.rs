@@ -1,7 +1,7 @@
 use crate::*;
 use crate::player::Player;
 
-#[derive(BorshDeserialize, BorshSerialize, PartialEq)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Debug)]
 pub enum PieceType {
 	Man,
 	King
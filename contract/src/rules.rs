@@ -0,0 +1,597 @@
+use std::cmp::Reverse;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::*;
+use crate::ai::bitboard::BitBoard;
+use crate::board::BoardToSave;
+
+/// The checkers variant a game is played with
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub enum GameVariant {
+    /// American checkers, also known as English draughts
+    #[default]
+    American,
+
+    /// International draughts, played on a 10x10 board
+    International,
+
+    /// Russian draughts (Shashki)
+    Russian,
+
+    /// Italian draughts, with strict capture priorities
+    Italian,
+
+    /// Turkish draughts, where pieces move orthogonally
+    Turkish,
+
+    /// Giveaway checkers, played like American checkers but the player
+    /// left without moves wins
+    Giveaway,
+}
+
+impl GameVariant {
+    pub fn rules(&self) -> &'static dyn RuleSet {
+        match self {
+            GameVariant::American => &AmericanRules,
+            GameVariant::International => &InternationalRules,
+            GameVariant::Russian => &RussianRules,
+            GameVariant::Italian => &ItalianRules,
+            GameVariant::Turkish => &TurkishRules,
+            GameVariant::Giveaway => &GiveawayRules,
+        }
+    }
+}
+
+/// Describes how a checkers variant is played: the board, how pieces move
+/// and capture, and how the game ends.
+pub trait RuleSet {
+    /// Number of rows and columns of the board
+    fn board_size(&self) -> usize;
+
+    /// Whether pieces may stand on the square, by default the dark squares
+    /// where the pieces start
+    fn is_playable_square(&self, row: usize, col: usize) -> bool {
+        row % 2 == col % 2
+    }
+
+    /// Number of the square in draughts notation, None if pieces can't stand
+    /// on it. By default the squares are numbered row by row from the back row
+    /// of the first player, as in American checkers where the first player is
+    /// Black and starts on squares 1-12.
+    fn square_number(&self, row: usize, col: usize) -> Option<usize> {
+        let size = self.board_size();
+        if row >= size || col >= size || !self.is_playable_square(row, col) {
+            return None;
+        }
+        Some(row * size / 2 + (size - 1 - col) / 2 + 1)
+    }
+
+    /// Position of the square with the given number in draughts notation
+    fn square_position(&self, number: usize) -> Option<BoardPosition> {
+        let size = self.board_size();
+        (0..size)
+            .flat_map(|row| (0..size).map(move |col| BoardPosition::new(row, col)))
+            .find(|position| self.square_number(position.row, position.column) == Some(number))
+    }
+
+    /// Creates a board with the pieces of both players at their starting positions.
+    /// `player1` moves first, from the lower rows of the board.
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave;
+
+    /// Determines the simple moves available to the piece at the given position.
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove>;
+
+    /// Determines the jumps available to the piece at the given position.
+    /// The returned tree has no jumps if the piece can't capture.
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove;
+
+    /// Determines the simple moves available to all the pieces of `player`,
+    /// visiting the squares of the board row by row.
+    fn find_all_simple_moves(&self,
+                             board: &Board,
+                             player: &Player,
+                             direction: Direction)
+                             -> Vec<SimpleMove> {
+        let mut moves = Vec::new();
+
+        for r in 0..board.number_rows() {
+            for c in 0..board.number_columns() {
+                if let Some(piece) = board.get_tile(r, c).get_piece()
+                    .filter(|piece| piece.get_player_id() == player.id) {
+                    let piece_moves = self.find_simple_moves(
+                        board, piece.get_type(), direction, r, c);
+                    moves.extend(piece_moves);
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Determines the jumps available to all the pieces of `player`, visiting
+    /// the squares of the board row by row. Pieces that can't capture are left out.
+    fn find_all_jump_moves(&self,
+                           board: &Board,
+                           player: &Player,
+                           direction: Direction)
+                           -> Vec<JumpMove> {
+        let mut moves = Vec::new();
+
+        for r in 0..board.number_rows() {
+            for c in 0..board.number_columns() {
+                if let Some(piece) = board.get_tile(r, c).get_piece()
+                    .filter(|piece| piece.get_player_id() == player.id) {
+                    let jump_move = self.find_jump_moves(
+                        board, player, piece.get_type(), direction, r, c);
+                    if !jump_move.jumps().is_empty() {
+                        moves.push(jump_move);
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Whether a player who is able to capture has to do so
+    fn is_capture_mandatory(&self) -> bool {
+        true
+    }
+
+    /// Keeps the jumps the current player is allowed to choose from,
+    /// given all the jumps available to their pieces.
+    fn filter_jump_moves(&self, _board: &Board, jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+        jump_moves
+    }
+
+    /// Whether a man moving in `direction` is promoted to king on `row`
+    fn is_promotion_row(&self, board: &Board, direction: Direction, row: usize) -> bool {
+        match direction {
+            Direction::IncreasingRank => row + 1 == board.number_rows(),
+            Direction::DecreasingRank => row == 0
+        }
+    }
+
+    /// Whether a man reaching the promotion row in the middle of a capture
+    /// is promoted at once and continues capturing as a king
+    fn promotes_during_capture(&self) -> bool {
+        false
+    }
+
+    /// Number of moves in a row without a capture or a man move after which the game is drawn
+    fn draw_moves_without_progress(&self) -> u32 {
+        DRAW_MOVES_WITHOUT_PROGRESS
+    }
+
+    /// Decides whether the game is over after a move. It is called once the
+    /// next player is selected and their available moves are computed.
+    fn game_state(&self, game: &Game) -> GameState {
+        if game.is_game_over() {
+            GameState::GameOver { winner_id: (1 - game.current_player_index) }
+        } else if game.is_drawn_position() {
+            GameState::Draw
+        } else {
+            GameState::InProgress
+        }
+    }
+}
+
+/// American checkers: 8x8 board, men move and capture forward only,
+/// kings move and capture one square in any diagonal direction.
+pub struct AmericanRules;
+
+impl RuleSet for AmericanRules {
+    fn board_size(&self) -> usize {
+        CHECKERBOARD_SIZE
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        BoardToSave::new_checkerboard(CHECKERBOARD_SIZE, 3, player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        match piece_type {
+            PieceType::Man => ai::find_simple_moves_for_man(board, direction, row, col),
+            PieceType::King => ai::find_simple_moves_for_king(board, row, col),
+        }
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        match piece_type {
+            PieceType::Man => ai::find_jump_moves_for_man(board, player, direction, row, col),
+            PieceType::King => ai::find_jump_moves_for_king(board, player, row, col),
+        }
+    }
+
+    // The bitboard generator gives the same moves as the per-square one, for less gas
+    fn find_all_simple_moves(&self,
+                             board: &Board,
+                             player: &Player,
+                             direction: Direction)
+                             -> Vec<SimpleMove> {
+        BitBoard::new(board).find_simple_moves(player, direction)
+    }
+
+    fn find_all_jump_moves(&self,
+                           board: &Board,
+                           player: &Player,
+                           direction: Direction)
+                           -> Vec<JumpMove> {
+        BitBoard::new(board).find_jump_moves(player, direction)
+    }
+}
+
+/// International draughts: 10x10 board with 20 men per side. Men move
+/// forward but capture forward and backward, kings fly, and a player must
+/// take the jump that captures the most pieces.
+pub struct InternationalRules;
+
+impl RuleSet for InternationalRules {
+    fn board_size(&self) -> usize {
+        INTERNATIONAL_BOARD_SIZE
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        BoardToSave::new_checkerboard(INTERNATIONAL_BOARD_SIZE, 4, player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        match piece_type {
+            PieceType::Man => ai::find_simple_moves_for_man(board, direction, row, col),
+            PieceType::King => ai::find_simple_moves_for_flying_king(board, row, col),
+        }
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       _direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        match piece_type {
+            PieceType::Man => ai::find_jump_moves_for_man_in_all_directions(board, player, row, col),
+            PieceType::King => ai::find_jump_moves_for_flying_king(board, player, row, col),
+        }
+    }
+
+    fn filter_jump_moves(&self, _board: &Board, jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+        retain_majority_captures(jump_moves)
+    }
+
+    // The first player is White and starts on squares 31-50
+    fn square_number(&self, row: usize, col: usize) -> Option<usize> {
+        let size = self.board_size();
+        if row >= size || col >= size || !self.is_playable_square(row, col) {
+            return None;
+        }
+        Some((size - 1 - row) * size / 2 + col / 2 + 1)
+    }
+
+    // 25 moves by each player
+    fn draw_moves_without_progress(&self) -> u32 {
+        50
+    }
+}
+
+/// Russian draughts: 8x8 board, men capture forward and backward, kings fly,
+/// and a man reaching the last row during a capture goes on capturing as a king.
+/// Any capture sequence may be chosen, but it has to be completed.
+pub struct RussianRules;
+
+impl RuleSet for RussianRules {
+    fn board_size(&self) -> usize {
+        CHECKERBOARD_SIZE
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        BoardToSave::new_checkerboard(CHECKERBOARD_SIZE, 3, player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        match piece_type {
+            PieceType::Man => ai::find_simple_moves_for_man(board, direction, row, col),
+            PieceType::King => ai::find_simple_moves_for_flying_king(board, row, col),
+        }
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        match piece_type {
+            PieceType::Man => ai::find_jump_moves_for_man_promoting_during_capture(
+                board, player, direction, row, col),
+            PieceType::King => ai::find_jump_moves_for_flying_king(board, player, row, col),
+        }
+    }
+
+    fn promotes_during_capture(&self) -> bool {
+        true
+    }
+}
+
+/// Italian draughts: 8x8 board, men move and capture forward only and
+/// may not capture kings, kings move one square. Among the available
+/// captures a player has to take, in order of priority, the one that
+/// captures the most pieces, then the one made with a king, then the one
+/// that captures the most kings, then the one that meets a king first.
+pub struct ItalianRules;
+
+impl RuleSet for ItalianRules {
+    fn board_size(&self) -> usize {
+        CHECKERBOARD_SIZE
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        BoardToSave::new_checkerboard(CHECKERBOARD_SIZE, 3, player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        match piece_type {
+            PieceType::Man => ai::find_simple_moves_for_man(board, direction, row, col),
+            PieceType::King => ai::find_simple_moves_for_king(board, row, col),
+        }
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        match piece_type {
+            PieceType::Man => ai::find_jump_moves_for_man_not_capturing_kings(
+                board, player, direction, row, col),
+            PieceType::King => ai::find_jump_moves_for_king(board, player, row, col),
+        }
+    }
+
+    fn filter_jump_moves(&self, board: &Board, jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+        retain_priority_captures(board, jump_moves)
+    }
+}
+
+/// Turkish draughts: 8x8 board where every square is used and pieces move
+/// along rows and columns. Men move and capture forward and sideways, kings
+/// fly, captured pieces are removed one by one, and a player must take the
+/// jump that captures the most pieces.
+pub struct TurkishRules;
+
+impl RuleSet for TurkishRules {
+    fn board_size(&self) -> usize {
+        CHECKERBOARD_SIZE
+    }
+
+    fn is_playable_square(&self, _row: usize, _col: usize) -> bool {
+        true
+    }
+
+    // Every square is used, numbered row by row from 1 to 64
+    fn square_number(&self, row: usize, col: usize) -> Option<usize> {
+        let size = self.board_size();
+        if row >= size || col >= size {
+            return None;
+        }
+        Some(row * size + col + 1)
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        BoardToSave::new_orthogonal_board(CHECKERBOARD_SIZE, 2, player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        match piece_type {
+            PieceType::Man => ai::find_orthogonal_simple_moves_for_man(board, direction, row, col),
+            PieceType::King => ai::find_orthogonal_simple_moves_for_flying_king(board, row, col),
+        }
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        match piece_type {
+            PieceType::Man => ai::find_orthogonal_jump_moves_for_man(board, player, direction, row, col),
+            PieceType::King => ai::find_orthogonal_jump_moves_for_flying_king(board, player, row, col),
+        }
+    }
+
+    fn filter_jump_moves(&self, _board: &Board, jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+        retain_majority_captures(jump_moves)
+    }
+}
+
+/// Giveaway checkers (antidraughts): the moves of American checkers with
+/// the winning condition reversed. The player who has no legal move left,
+/// including when all their pieces were captured, wins.
+pub struct GiveawayRules;
+
+impl RuleSet for GiveawayRules {
+    fn board_size(&self) -> usize {
+        AmericanRules.board_size()
+    }
+
+    fn new_board(&self, player1: &Player, player2: &Player) -> BoardToSave {
+        AmericanRules.new_board(player1, player2)
+    }
+
+    fn find_simple_moves(&self,
+                         board: &Board,
+                         piece_type: PieceType,
+                         direction: Direction,
+                         row: usize,
+                         col: usize)
+                         -> Vec<SimpleMove> {
+        AmericanRules.find_simple_moves(board, piece_type, direction, row, col)
+    }
+
+    fn find_jump_moves(&self,
+                       board: &Board,
+                       player: &Player,
+                       piece_type: PieceType,
+                       direction: Direction,
+                       row: usize,
+                       col: usize)
+                       -> JumpMove {
+        AmericanRules.find_jump_moves(board, player, piece_type, direction, row, col)
+    }
+
+    fn find_all_simple_moves(&self,
+                             board: &Board,
+                             player: &Player,
+                             direction: Direction)
+                             -> Vec<SimpleMove> {
+        AmericanRules.find_all_simple_moves(board, player, direction)
+    }
+
+    fn find_all_jump_moves(&self,
+                           board: &Board,
+                           player: &Player,
+                           direction: Direction)
+                           -> Vec<JumpMove> {
+        AmericanRules.find_all_jump_moves(board, player, direction)
+    }
+
+    fn game_state(&self, game: &Game) -> GameState {
+        if game.is_game_over() {
+            GameState::GameOver { winner_id: game.current_player_index }
+        } else if game.is_drawn_position() {
+            GameState::Draw
+        } else {
+            GameState::InProgress
+        }
+    }
+}
+
+// Ranks an Italian capture sequence, a greater value has a higher priority
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct CapturePriority {
+    captures: usize,
+    with_king: bool,
+    kings_captured: usize,
+    // index of the first king captured, sequences capturing no king rank last
+    first_king_captured: Reverse<usize>,
+}
+
+impl CapturePriority {
+    fn new(board: &Board, sequence: &[BoardPosition]) -> CapturePriority {
+        let is_king = |position: &BoardPosition| board.get_tile(position.row, position.column)
+            .get_piece()
+            .map(|piece| piece.get_type() == PieceType::King)
+            .unwrap_or(false);
+
+        let captured: Vec<BoardPosition> = sequence.iter()
+            .zip(sequence.iter().skip(1))
+            .flat_map(|(from, to)| from.positions_between(to))
+            .filter(|position| board.get_tile(position.row, position.column).get_piece().is_some())
+            .collect();
+
+        CapturePriority {
+            captures: captured.len(),
+            with_king: is_king(&sequence[0]),
+            kings_captured: captured.iter().filter(|position| is_king(position)).count(),
+            first_king_captured: Reverse(captured.iter().position(is_king).unwrap_or(usize::MAX)),
+        }
+    }
+}
+
+// Keeps only the jump sequences with the highest Italian priority
+fn retain_priority_captures(board: &Board, jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+    let best_priority = jump_moves.iter()
+        .flat_map(|jump_move| jump_move.sequences())
+        .map(|sequence| CapturePriority::new(board, &sequence))
+        .max();
+
+    let best_priority = match best_priority {
+        Some(priority) => priority,
+        None => return jump_moves,
+    };
+
+    jump_moves.into_iter()
+        .filter_map(|mut jump_move| {
+            let retained = jump_move.retain_sequences(
+                &|sequence: &[BoardPosition]| CapturePriority::new(board, sequence) == best_priority);
+            if retained { Some(jump_move) } else { None }
+        })
+        .collect()
+}
+
+// Keeps only the jump sequences capturing the most pieces
+fn retain_majority_captures(jump_moves: Vec<JumpMove>) -> Vec<JumpMove> {
+    let max_captures = jump_moves.iter()
+        .map(|jump_move| jump_move.max_captures())
+        .max()
+        .unwrap_or(0);
+
+    jump_moves.into_iter()
+        .filter(|jump_move| jump_move.max_captures() == max_captures)
+        .map(|mut jump_move| {
+            jump_move.retain_captures(max_captures);
+            jump_move
+        })
+        .collect()
+}
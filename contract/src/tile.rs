@@ -2,12 +2,28 @@ use crate::*;
 use std::ops::Deref;
 use crate::piece::Piece;
 
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy)]
 pub struct TileToSave {
     pub player_id: u32,
     pub piece_type: PieceType
 }
 
+impl Tile for TileToSave {
+    fn get_piece(&self) -> Option<&dyn Piece> {
+       Option::Some(self)
+    }
+}
+
+impl Piece for TileToSave {
+    fn get_player_id(&self) -> u32 {
+        self.player_id
+    }
+
+    fn get_type(&self) -> PieceType {
+        self.piece_type
+    }
+}
+
 pub trait Tile {
     fn get_piece(&self) -> Option<&dyn Piece>;
 }
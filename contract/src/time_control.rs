@@ -0,0 +1,209 @@
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::*;
+
+const NANOSECONDS_PER_SECOND: Timestamp = 1_000_000_000;
+const ONE_DAY: Timestamp = 24 * ONE_HOUR;
+
+/// Time given back to a player for each move they make
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TimeBonus {
+    /// Fischer increment: the seconds are added to the clock after each move
+    Increment(u64),
+
+    /// Bronstein delay: the first seconds of each move are not taken from the clock
+    Delay(u64),
+}
+
+/// A clock for the whole game, all in seconds
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClockControl {
+    /// Time on the clock of each player when the game starts
+    pub initial_seconds: u64,
+    pub bonus: Option<TimeBonus>,
+    /// Longest time a single move may take, whatever is left on the clock
+    pub max_move_seconds: Option<u64>,
+}
+
+impl Default for ClockControl {
+    /// The hour each player had for the whole game before time controls
+    fn default() -> Self {
+        ClockControl {
+            initial_seconds: ONE_HOUR / NANOSECONDS_PER_SECOND,
+            bonus: None,
+            max_move_seconds: None,
+        }
+    }
+}
+
+/// Days for each move, for slow games
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CorrespondenceControl {
+    pub days_per_move: u64,
+    /// Days each player has in the bank for the whole game. The player to
+    /// move spends them with `take_vacation`, which pushes the deadline of
+    /// their move back by as many days.
+    pub vacation_days: u64,
+}
+
+/// How much time the players have for their moves
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TimeControl {
+    Clock(ClockControl),
+    Correspondence(CorrespondenceControl),
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl::Clock(ClockControl::default())
+    }
+}
+
+impl TimeControl {
+    pub(crate) fn assert_valid(&self) {
+        match self {
+            TimeControl::Clock(clock) => {
+                assert!(clock.initial_seconds > 0, "The initial time must be positive");
+                assert_ne!(clock.max_move_seconds, Some(0), "The maximum time of a move must be positive");
+            }
+            TimeControl::Correspondence(correspondence) => {
+                assert!(correspondence.days_per_move > 0, "The days per move must be positive");
+            }
+        }
+    }
+
+    /// Time on the clock of each player when the game starts: their vacation days in correspondence games
+    pub(crate) fn initial_time(&self) -> Timestamp {
+        match self {
+            TimeControl::Clock(clock) => clock.initial_seconds * NANOSECONDS_PER_SECOND,
+            TimeControl::Correspondence(correspondence) => correspondence.vacation_days * ONE_DAY,
+        }
+    }
+
+    /// Time added to the clock of a player after each of their moves
+    pub(crate) fn increment(&self) -> Timestamp {
+        match self {
+            TimeControl::Clock(ClockControl { bonus: Some(TimeBonus::Increment(seconds)), .. }) =>
+                seconds * NANOSECONDS_PER_SECOND,
+            _ => 0,
+        }
+    }
+
+    /// Time taken from the clock for a move that took `elapsed`
+    pub(crate) fn charged_time(&self, elapsed: Timestamp) -> Timestamp {
+        match self {
+            TimeControl::Clock(ClockControl { bonus: Some(TimeBonus::Delay(seconds)), .. }) =>
+                elapsed.saturating_sub(seconds * NANOSECONDS_PER_SECOND),
+            TimeControl::Clock(_) => elapsed,
+            // vacation days are only spent when the player takes them
+            TimeControl::Correspondence(_) => 0,
+        }
+    }
+
+    /// Time that `days` of vacation push the deadline of a move back by
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time control isn't a correspondence one.
+    pub(crate) fn vacation_time(&self, days: u64) -> Timestamp {
+        match self {
+            TimeControl::Clock(_) => panic!("Vacation days are only taken in correspondence games"),
+            TimeControl::Correspondence(_) => days * ONE_DAY,
+        }
+    }
+
+    /// Whole vacation days in `remaining`, none in games played with a clock
+    pub(crate) fn vacation_days(&self, remaining: Timestamp) -> Option<u64> {
+        match self {
+            TimeControl::Clock(_) => None,
+            TimeControl::Correspondence(_) => Some(remaining / ONE_DAY),
+        }
+    }
+
+    /// Longest time a move may take when the player has `remaining` on the clock
+    pub(crate) fn move_time_limit(&self, remaining: Timestamp) -> Timestamp {
+        match self {
+            TimeControl::Clock(clock) => {
+                let delay = match clock.bonus {
+                    Some(TimeBonus::Delay(seconds)) => seconds * NANOSECONDS_PER_SECOND,
+                    _ => 0,
+                };
+                let limit = remaining + delay;
+                clock.max_move_seconds
+                    .map(|seconds| limit.min(seconds * NANOSECONDS_PER_SECOND))
+                    .unwrap_or(limit)
+            }
+            TimeControl::Correspondence(correspondence) => correspondence.days_per_move * ONE_DAY,
+        }
+    }
+
+    /// Whether a player who had `remaining` on the clock lost on time after
+    /// thinking `elapsed` on their move
+    pub(crate) fn is_out_of_time(&self, remaining: Timestamp, elapsed: Timestamp) -> bool {
+        elapsed > self.move_time_limit(remaining)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECOND: Timestamp = NANOSECONDS_PER_SECOND;
+
+    #[test]
+    fn fischer_increment() {
+        let blitz = TimeControl::Clock(
+            ClockControl { initial_seconds: 180, bonus: Some(TimeBonus::Increment(2)), max_move_seconds: None });
+        assert_eq!(180 * SECOND, blitz.initial_time());
+        assert_eq!(2 * SECOND, blitz.increment());
+        assert_eq!(5 * SECOND, blitz.charged_time(5 * SECOND));
+        assert!(!blitz.is_out_of_time(180 * SECOND, 180 * SECOND));
+        assert!(blitz.is_out_of_time(180 * SECOND, 180 * SECOND + 1));
+    }
+
+    #[test]
+    fn bronstein_delay() {
+        let rapid = TimeControl::Clock(
+            ClockControl { initial_seconds: 600, bonus: Some(TimeBonus::Delay(5)), max_move_seconds: None });
+        assert_eq!(0, rapid.increment());
+        assert_eq!(0, rapid.charged_time(3 * SECOND));
+        assert_eq!(2 * SECOND, rapid.charged_time(7 * SECOND));
+        // the delay runs before the clock does
+        assert!(!rapid.is_out_of_time(0, 5 * SECOND));
+        assert!(rapid.is_out_of_time(0, 5 * SECOND + 1));
+    }
+
+    #[test]
+    fn max_move_time() {
+        let control = TimeControl::Clock(
+            ClockControl { initial_seconds: 3600, bonus: None, max_move_seconds: Some(60) });
+        assert!(!control.is_out_of_time(3600 * SECOND, 60 * SECOND));
+        assert!(control.is_out_of_time(3600 * SECOND, 60 * SECOND + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Vacation days are only taken in correspondence games")]
+    fn no_vacation_with_a_clock() {
+        TimeControl::default().vacation_time(1);
+    }
+
+    #[test]
+    fn correspondence() {
+        let control = TimeControl::Correspondence(CorrespondenceControl { days_per_move: 3, vacation_days: 5 });
+        assert_eq!(5 * ONE_DAY, control.initial_time());
+        assert_eq!(0, control.increment());
+        // the vacation days in the bank aren't spent by a late move
+        assert_eq!(0, control.charged_time(5 * ONE_DAY));
+        assert_eq!(2 * ONE_DAY, control.vacation_time(2));
+        assert_eq!(Some(5), control.vacation_days(5 * ONE_DAY));
+        assert_eq!(None, TimeControl::default().vacation_days(ONE_HOUR));
+
+        assert_eq!(3 * ONE_DAY, control.move_time_limit(5 * ONE_DAY));
+        assert!(!control.is_out_of_time(5 * ONE_DAY, 3 * ONE_DAY));
+        assert!(control.is_out_of_time(5 * ONE_DAY, 3 * ONE_DAY + 1));
+    }
+}
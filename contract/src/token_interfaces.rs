@@ -57,8 +57,30 @@ pub struct WhitelistedToken {
     pub balances: FungibleTokenBalances
 }
 
+/// Options of a game played for fungible tokens, sent in the msg of `ft_transfer_call`
+#[derive(Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtGameOptions {
+    #[serde(default)]
+    pub variant: GameVariant,
+    #[serde(default)]
+    pub disable_takebacks: bool,
+    #[serde(default)]
+    pub time_control: TimeControl,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct TransferMessage {
+    #[serde(default)]
+    referrer_id: Option<AccountId>,
+    #[serde(flatten)]
+    options: FtGameOptions,
+}
+
 enum TransferInstruction {
     DepositWithRefferer,
+    DepositWithOptions,
     Deposit,
 }
 
@@ -66,11 +88,13 @@ enum TransferInstruction {
 /*
 if msg in ft_transfer call is empty - its default deposit with no refferer
 if msg sended in format : 'account.testnet' - it inserts in make_available_ft function to add some refferal to available player
+if msg is a json object : '{"referrer_id": "account.testnet", "variant": "International", "disable_takebacks": true, "time_control": {"Clock": {"initial_seconds": 300, "bonus": null, "max_move_seconds": null}}}' - the game options, all of them optional
 */
 impl From<String> for TransferInstruction {
     fn from(item: String) -> Self {
         match &item[..] {
             "" => TransferInstruction::Deposit,
+            _ if item.starts_with('{') => TransferInstruction::DepositWithOptions,
             _ => TransferInstruction::DepositWithRefferer,
         }
     }
@@ -261,7 +285,7 @@ impl Checkers {
                 let referrer_id: Option<AccountId> = None;
 
                 log!("in deposit from @{} with token: ${} amount {:?} ", sender, ticker, amount);
-                let available_complete = self.make_available_ft(sender, amount, referrer_id);
+                let available_complete = self.make_available_ft(sender, amount, referrer_id, FtGameOptions::default());
                 if available_complete {
                     PromiseOrValue::Value(U128(0))
                 } else {
@@ -283,7 +307,21 @@ impl Checkers {
                     referrer_id
                 );
 
-                let available_complete =self.make_available_ft(sender, amount, Some(referrer_id));
+                let available_complete =self.make_available_ft(sender, amount, Some(referrer_id), FtGameOptions::default());
+                if available_complete {
+                    PromiseOrValue::Value(U128(0))
+                } else {
+                    PromiseOrValue::Value(amount)
+                }
+            }
+            TransferInstruction::DepositWithOptions => {
+                let message: TransferMessage = near_sdk::serde_json::from_str(&msg).expect("Invalid game options in msg");
+                if let Some(referrer_id) = &message.referrer_id {
+                    assert!(env::is_valid_account_id(referrer_id.as_bytes()), "Refferal account @{} is invalid", referrer_id);
+                }
+
+                log!("in deposit from @{} with token: ${} amount {:?} with options", sender, ticker, amount);
+                let available_complete = self.make_available_ft(sender, amount, message.referrer_id, message.options);
                 if available_complete {
                     PromiseOrValue::Value(U128(0))
                 } else {
@@ -306,3 +344,30 @@ pub fn min_deposit(decimals: u8) -> Balance {
     10u128.pow((decimals - 1).into())
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn game_options_in_transfer_message() {
+        let message: TransferMessage = near_sdk::serde_json::from_str(
+            r#"{"referrer_id": "alice.testnet", "variant": "International"}"#).unwrap();
+        assert_eq!(Some("alice.testnet".to_string()), message.referrer_id);
+        assert_eq!(GameVariant::International, message.options.variant);
+        assert!(!message.options.disable_takebacks);
+
+        let message: TransferMessage = near_sdk::serde_json::from_str("{}").unwrap();
+        assert_eq!(None, message.referrer_id);
+        assert_eq!(GameVariant::American, message.options.variant);
+
+        let message: TransferMessage = near_sdk::serde_json::from_str(r#"{"disable_takebacks": true}"#).unwrap();
+        assert!(message.options.disable_takebacks);
+        assert_eq!(TimeControl::default(), message.options.time_control);
+
+        let message: TransferMessage = near_sdk::serde_json::from_str(
+            r#"{"time_control": {"Correspondence": {"days_per_move": 3, "vacation_days": 10}}}"#).unwrap();
+        assert_eq!(TimeControl::Correspondence(CorrespondenceControl { days_per_move: 3, vacation_days: 10 }),
+                   message.options.time_control);
+    }
+}
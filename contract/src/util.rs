@@ -32,9 +32,11 @@ pub fn apply_positions_as_move (game : &mut Game, positions : Vec<BoardPosition>
 		let row_diff = util::absolute_diff(start.row, end.row);
 		let col_diff = util::absolute_diff(start.column, end.column);
 
-		if row_diff == 1 && col_diff == 1 {
-			game.apply_simple_move(SimpleMove::new(
-				start.row, start.column, end.row, end.column))
+		// flying kings make simple moves over more than one tile, and in
+		// orthogonal variants pieces move along rows and columns
+		let simple_move = SimpleMove::new(start.row, start.column, end.row, end.column);
+		if (row_diff == 1 && col_diff == 1) || game.available_simple_moves.contains(&simple_move) {
+			game.apply_simple_move(simple_move)
 		} else {
 			game.apply_jump_move(positions)
 		}
@@ -43,6 +45,107 @@ pub fn apply_positions_as_move (game : &mut Game, positions : Vec<BoardPosition>
 	}
 }
 
+/// Plays a move as `make_move` would, and describes why it was rejected
+/// along with the moves the player could make instead
+pub fn validate_move (game : &mut Game, line : &str) -> MoveValidation {
+	let reject = |game : &mut Game, error : MoveErrorKind, message : String| {
+		let must_jump = !game.available_jump_moves.is_empty() && game.rules().is_capture_mandatory();
+		MoveValidation {
+			is_valid : false,
+			error : Some(error),
+			message : Some(message),
+			simple_moves : if must_jump { Vec::new() } else { std::mem::take(&mut game.available_simple_moves) },
+			jump_moves : std::mem::take(&mut game.available_jump_moves)
+		}
+	};
+
+	if game.is_finished() {
+		return MoveValidation {
+			is_valid : false,
+			error : Some(MoveErrorKind::GameOver),
+			message : Some("Game already finished".to_string()),
+			simple_moves : Vec::new(),
+			jump_moves : Vec::new()
+		};
+	}
+
+	let positions = match input::parse_move(line, game.rules()) {
+		Ok(positions) => positions,
+		Err(error) => {
+			let out_of_bounds = matches!(&error, InputError::InvalidTokens { tokens }
+				if tokens.iter().any(|token| matches!(token,
+					TokenError::OutOfBounds { .. } | TokenError::InvalidSquareNumber { .. })));
+			let kind = if out_of_bounds { MoveErrorKind::OutOfBounds } else { MoveErrorKind::ParseError };
+			return reject(game, kind, input_error_message(&error));
+		}
+	};
+
+	let start = positions[0];
+	let player_id = game.current_player().id;
+	let own_piece = game.board().get_tile(start.row, start.column).get_piece()
+		.map(|piece| piece.get_player_id() == player_id)
+		.unwrap_or(false);
+	if !own_piece {
+		return reject(game, MoveErrorKind::NotYourPiece,
+			format!("There is no piece of yours on the first board position of '{}'", line));
+	}
+
+	// the game is a copy, playing the move only checks it
+	match apply_positions_as_move(game, positions) {
+		Ok(_) => MoveValidation {
+			is_valid : true,
+			error : None,
+			message : None,
+			simple_moves : Vec::new(),
+			jump_moves : Vec::new()
+		},
+		Err(error) => {
+			let kind = match error {
+				MoveError::InvalidMove => MoveErrorKind::IllegalMove,
+				MoveError::ShouldHaveJumped => MoveErrorKind::MustJump,
+				MoveError::IncompleteJump => MoveErrorKind::IncompleteJump,
+				MoveError::NotPriorityCapture => MoveErrorKind::NotPriorityCapture
+			};
+			reject(game, kind, move_error_message(&error).to_string())
+		}
+	}
+}
+
+/// Describes why a move couldn't be read, from its first invalid token
+pub fn input_error_message (error : &InputError) -> String {
+	match error {
+		InputError::TooFewTokens =>
+			"You must specify at least two board positions".to_string(),
+		InputError::InvalidTokens { tokens } => match tokens.first() {
+			Some(TokenError::MissingFile { token }) =>
+				format!("Board position '{}' must specify file", token),
+			Some(TokenError::MissingRank { token }) =>
+				format!("Board position '{}' must specify rank", token),
+			Some(TokenError::ZeroRank { token }) =>
+				format!("Rank cannot be zero: {}", token),
+			Some(TokenError::InvalidCharacter { token, char_index }) => {
+				let ch = token.chars().nth(*char_index).unwrap();
+				format!("Board position '{}' contains invalid character '{}'", token, ch)
+			}
+			Some(TokenError::OutOfBounds { token }) =>
+				format!("Board position '{}' is outside the board", token),
+			Some(TokenError::InvalidSquareNumber { token }) =>
+				format!("There is no square number {} on the board", token),
+			None => "Invalid move".to_string()
+		}
+	}
+}
+
+/// Describes why a move isn't legal
+pub fn move_error_message (error : &MoveError) -> &'static str {
+	match error {
+		MoveError::InvalidMove => "Illegal move",
+		MoveError::ShouldHaveJumped => "Must take jump",
+		MoveError::IncompleteJump => "Must complete the capture sequence",
+		MoveError::NotPriorityCapture => "Must take the capture with the highest priority"
+	}
+}
+
 /*
 pub enum PlayerColor {
     Red,
@@ -64,6 +167,26 @@ pub fn player_color_to_name(color : PlayerColor) -> &'static str {
 	}
 }*/
 
+/// The deposit attached to the call. `env::attached_deposit` of near-sdk 3.1
+/// reads it into an array it never declares as written, which newer
+/// compilers fold to 0 in wasm, so the contract asks the host itself.
+#[cfg(target_arch = "wasm32")]
+pub fn attached_deposit() -> Balance {
+	extern "C" {
+		#[link_name = "attached_deposit"]
+		fn host_attached_deposit(balance_ptr : u64);
+	}
+
+	let mut data = [0u8; std::mem::size_of::<Balance>()];
+	unsafe { host_attached_deposit(data.as_mut_ptr() as u64) };
+	Balance::from_le_bytes(data)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn attached_deposit() -> Balance {
+	env::attached_deposit()
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -72,4 +195,55 @@ mod test {
 		let result = absolute_diff(a, b);
 		assert_eq!(expected, result);
 	}
+
+	#[test]
+	fn validate_legal_and_illegal_moves() {
+		let mut game = Game::new("alice".into(), "bob".into(),
+			TokenBalance { token_id : None, balance : 0 }, GameVariant::American);
+
+		let validation = validate_move(&mut game, "11-15");
+		assert!(validation.is_valid);
+		assert_eq!(None, validation.error);
+
+		let mut game = Game::new("alice".into(), "bob".into(),
+			TokenBalance { token_id : None, balance : 0 }, GameVariant::American);
+		let validation = validate_move(&mut game, "9-18");
+		assert_eq!(Some(MoveErrorKind::IllegalMove), validation.error);
+		assert_eq!(7, validation.simple_moves.len());
+		assert!(validation.jump_moves.is_empty());
+
+		let mut game = Game::new("alice".into(), "bob".into(),
+			TokenBalance { token_id : None, balance : 0 }, GameVariant::American);
+		assert_eq!(Some(MoveErrorKind::NotYourPiece), validate_move(&mut game, "22-18").error);
+		assert_eq!(Some(MoveErrorKind::NotYourPiece), validate_move(&mut game, "15-19").error);
+		assert_eq!(Some(MoveErrorKind::OutOfBounds), validate_move(&mut game, "z9 a1").error);
+		assert_eq!(Some(MoveErrorKind::OutOfBounds), validate_move(&mut game, "11-33").error);
+		let validation = validate_move(&mut game, "c3+ d4");
+		assert_eq!(Some(MoveErrorKind::ParseError), validation.error);
+		assert_eq!(Some("Board position 'c3+' contains invalid character '+'".to_string()), validation.message);
+	}
+
+	#[test]
+	fn validate_captures() {
+		let mut game = Game::with_piece_positions(
+			vec![BoardPosition::new(2, 2), BoardPosition::new(0, 0)],
+			vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)]);
+		let validation = validate_move(&mut game, "a1 b2");
+		assert_eq!(Some(MoveErrorKind::MustJump), validation.error);
+		assert!(validation.simple_moves.is_empty());
+		assert_eq!(1, validation.jump_moves.len());
+
+		let mut game = Game::with_piece_positions(
+			vec![BoardPosition::new(2, 2)],
+			vec![BoardPosition::new(3, 3), BoardPosition::new(5, 5)]);
+		assert_eq!(Some(MoveErrorKind::IncompleteJump), validate_move(&mut game, "c3 e5").error);
+
+		let mut game = Game::with_piece_positions(
+			vec![BoardPosition::new(2, 2)],
+			vec![BoardPosition::new(3, 3)]);
+		game.winner_index = Some(0);
+		let validation = validate_move(&mut game, "c3 e5");
+		assert_eq!(Some(MoveErrorKind::GameOver), validation.error);
+	}
 }
+